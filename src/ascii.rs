@@ -0,0 +1,411 @@
+use crate::board::Board;
+use crate::search::{self, IndexedTileLayout, Operation};
+use crate::tile::Tile;
+
+// Drawings use the same characters as the comments throughout this crate.
+pub const PRESENT: char = 'X';
+pub const ABSENT: char = '·';
+// In a solution, a cell of the board that is not covered by any tile.
+pub const UNCOVERED: char = '_';
+
+// Tiles without a unique single-character name are drawn by their index instead.
+const INDEX_SYMBOLS: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    UnevenRows,
+    TooLarge,
+    UnexpectedCharacter(char),
+    // The cells of this tile do not form any of its layouts.
+    InvalidPiece(char),
+}
+
+// '.' is accepted as well, since '·' is hard to type.
+pub fn is_absent(c: char) -> bool {
+    c == ABSENT || c == '.'
+}
+
+// One entry per row, ignoring surrounding whitespace and empty lines.
+pub fn parse_grid(drawing: &str) -> Result<Vec<Vec<char>>, ParseError> {
+    let grid = drawing
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    if grid.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    if grid.iter().any(|row| row.len() != grid[0].len()) {
+        return Err(ParseError::UnevenRows);
+    }
+    Ok(grid)
+}
+
+// The character for each tile: Its name, if that is a single character which is unique among the
+// tiles and doesn't stand for a cell of the board. Otherwise its index, or the next symbol that is
+// still free. 'PRESENT' is never part of a solution, so a tile may well be called 'X'.
+pub fn tile_symbols(tiles: &[Tile]) -> Vec<char> {
+    let names = tiles
+        .iter()
+        .map(|tile| {
+            let mut chars = tile.get_name().chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c != UNCOVERED && !is_absent(c) => Some(c),
+                _ => None,
+            }
+        })
+        .collect::<Vec<_>>();
+    let names = names
+        .iter()
+        .map(|&name| name.filter(|_| names.iter().filter(|&&other| other == name).count() == 1))
+        .collect::<Vec<_>>();
+    let mut used = names.iter().flatten().copied().collect::<Vec<_>>();
+    names
+        .iter()
+        .enumerate()
+        .map(|(tile_index, &name)| {
+            name.unwrap_or_else(|| {
+                // Beyond that, the drawing is ambiguous, but still readable.
+                let symbol = INDEX_SYMBOLS
+                    .chars()
+                    .skip(tile_index)
+                    .chain(INDEX_SYMBOLS.chars())
+                    .find(|c| !used.contains(c))
+                    .unwrap_or('?');
+                used.push(symbol);
+                symbol
+            })
+        })
+        .collect()
+}
+
+// Blocked cells are drawn as 'ABSENT', and the cells covered by a tile with its name.
+pub fn render_solution(board: &Board, steps: &search::Result, tiles: &[Tile]) -> String {
+    let symbols = tile_symbols(tiles);
+    let mut grid = (0..board.height())
+        .map(|y| {
+            (0..board.width())
+                .map(|x| {
+                    if board.is_blocked_at(x, y) {
+                        ABSENT
+                    } else {
+                        UNCOVERED
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    for operation in steps {
+        let IndexedTileLayout {
+            tile_index,
+            layout_index,
+        } = operation.indexed_tile_layout;
+        let layout = &tiles[usize::from(tile_index)].get_layouts()[usize::from(layout_index)];
+        for (x, y) in layout.cells() {
+            grid[usize::from(y + operation.dy)][usize::from(x + operation.dx)] =
+                symbols[usize::from(tile_index)];
+        }
+    }
+    grid.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// The cells that can be reached from 'start' through edges, removing them from 'cells'.
+fn take_component(cells: &mut Vec<(u8, u8)>, start: (u8, u8)) -> Vec<(u8, u8)> {
+    let mut component = vec![start];
+    cells.retain(|&cell| cell != start);
+    let mut next = 0;
+    while next < component.len() {
+        let (x, y) = component[next];
+        next += 1;
+        let mut i = 0;
+        while i < cells.len() {
+            let (cx, cy) = cells[i];
+            if x.abs_diff(cx) + y.abs_diff(cy) == 1 {
+                component.push(cells.swap_remove(i));
+            } else {
+                i += 1;
+            }
+        }
+    }
+    component
+}
+
+// Covers 'cells' exactly with copies of the tile, by trying each layout at the first remaining cell
+// in reading order. Touching copies of the same tile are drawn with the same symbol, so a group
+// may consist of several pieces.
+fn cover_exactly(
+    cells: &[(u8, u8)],
+    tile: &Tile,
+    tile_index: u8,
+    steps: &mut Vec<Operation>,
+) -> bool {
+    let Some(&(x, y)) = cells.iter().min_by_key(|&&(x, y)| (y, x)) else {
+        return true;
+    };
+    for (layout_index, layout) in tile.get_layouts().iter().enumerate() {
+        let (first_x, first_y) = layout.cells().min_by_key(|&(x, y)| (y, x)).unwrap();
+        let (Some(dx), Some(dy)) = (x.checked_sub(first_x), y.checked_sub(first_y)) else {
+            continue;
+        };
+        let covered = layout
+            .cells()
+            .map(|(x, y)| Some((x.checked_add(dx)?, y.checked_add(dy)?)))
+            .collect::<Option<Vec<_>>>();
+        let Some(covered) = covered.filter(|covered| covered.iter().all(|c| cells.contains(c)))
+        else {
+            continue;
+        };
+        let rest = cells
+            .iter()
+            .copied()
+            .filter(|cell| !covered.contains(cell))
+            .collect::<Vec<_>>();
+        steps.push(Operation {
+            indexed_tile_layout: IndexedTileLayout {
+                tile_index,
+                layout_index: layout_index as u8,
+            },
+            dx,
+            dy,
+        });
+        if cover_exactly(&rest, tile, tile_index, steps) {
+            return true;
+        }
+        steps.pop();
+    }
+    false
+}
+
+// Inverse of 'render_solution'. Each connected group of cells with the same symbol consists of
+// one or more pieces of that tile.
+pub fn parse_solution(
+    drawing: &str,
+    tiles: &[Tile],
+) -> Result<(Board, search::Result), ParseError> {
+    let grid = parse_grid(drawing)?;
+    let symbols = tile_symbols(tiles);
+    let (width, height) = (grid[0].len(), grid.len());
+    if width > usize::from(u8::MAX)
+        || height > usize::from(u8::MAX)
+        || width * height > crate::board::MAX_CELLS as usize
+    {
+        return Err(ParseError::TooLarge);
+    }
+    let mut board = Board::all_blocked(width as u8, height as u8);
+    let mut cells_by_tile = vec![Vec::new(); tiles.len()];
+    for (y, row) in grid.iter().enumerate() {
+        for (x, &c) in row.iter().enumerate() {
+            let (x, y) = (x as u8, y as u8);
+            if is_absent(c) {
+                continue;
+            }
+            board.set_unblocked(x, y);
+            if c == UNCOVERED {
+                continue;
+            }
+            let tile_index = symbols
+                .iter()
+                .position(|&symbol| symbol == c)
+                .ok_or(ParseError::UnexpectedCharacter(c))?;
+            cells_by_tile[tile_index].push((x, y));
+        }
+    }
+    let mut steps = Vec::new();
+    for (tile_index, mut cells) in cells_by_tile.into_iter().enumerate() {
+        while let Some(&start) = cells.first() {
+            let component = take_component(&mut cells, start);
+            if !cover_exactly(&component, &tiles[tile_index], tile_index as u8, &mut steps) {
+                return Err(ParseError::InvalidPiece(symbols[tile_index]));
+            }
+        }
+    }
+    Ok((board, steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::{TileLayout, ALL_TILES};
+
+    #[test]
+    fn test_parse_grid() {
+        assert_eq!(parse_grid(""), Err(ParseError::Empty));
+        assert_eq!(parse_grid("\n  \n"), Err(ParseError::Empty));
+        assert_eq!(parse_grid("XX\nX"), Err(ParseError::UnevenRows));
+        assert_eq!(
+            parse_grid("\n  X·\n  ·X\n"),
+            Ok(vec![vec!['X', '·'], vec!['·', 'X']])
+        );
+    }
+
+    #[test]
+    fn test_render_solution() {
+        // Same as 'search::tests::test_sample_easy'.
+        let tiles = vec![
+            ALL_TILES[5].clone(),
+            ALL_TILES[6].clone(),
+            ALL_TILES[10].clone(),
+        ];
+        let board = "
+            ·XX··
+            XXXX·
+            ·XXXX
+            ·XXX·
+            ···X·
+        "
+        .parse::<Board>()
+        .unwrap();
+        let steps = vec![
+            Operation {
+                indexed_tile_layout: IndexedTileLayout {
+                    tile_index: 0,
+                    layout_index: 1,
+                },
+                dx: 2,
+                dy: 0,
+            },
+            Operation {
+                indexed_tile_layout: IndexedTileLayout {
+                    tile_index: 1,
+                    layout_index: 1,
+                },
+                dx: 2,
+                dy: 2,
+            },
+            Operation {
+                indexed_tile_layout: IndexedTileLayout {
+                    tile_index: 2,
+                    layout_index: 4,
+                },
+                dx: 0,
+                dy: 0,
+            },
+        ];
+        let drawing = render_solution(&board, &steps, &tiles);
+        assert_eq!(drawing, "·LF··\nLLFF·\n·LFGG\n·LGG·\n···_·");
+        let (parsed_board, mut parsed_steps) = parse_solution(&drawing, &tiles).unwrap();
+        assert_eq!(parsed_board, board);
+        parsed_steps.sort_by_key(|op| op.indexed_tile_layout.tile_index);
+        assert_eq!(parsed_steps, steps);
+    }
+
+    #[test]
+    fn test_render_by_index() {
+        // Custom tiles have no names.
+        let bar = TileLayout::from_rows(&[0x07]);
+        let tiles = vec![Tile::from_shape(&bar), Tile::from_shape(&bar)];
+        let (board, steps) = parse_solution("000\n111", &tiles).unwrap();
+        assert_eq!(board.count_unblocked(), 6);
+        assert_eq!(render_solution(&board, &steps, &tiles), "000\n111");
+        assert_eq!(
+            parse_solution("000\n111", &tiles[1..]),
+            Err(ParseError::UnexpectedCharacter('1'))
+        );
+        // Only the tiles without a usable name fall back to their index, or the next free symbol.
+        let named = |name: &str| Tile::from_shape(&bar).named(name);
+        let symbols = |tiles: &[Tile]| tile_symbols(tiles).into_iter().collect::<String>();
+        assert_eq!(symbols(&[named("A"), named("")]), "A1");
+        assert_eq!(symbols(&[named("1"), named("_"), named("X")]), "12X");
+        assert_eq!(symbols(&[named("A"), named("A"), named("BB")]), "012");
+    }
+
+    #[test]
+    fn test_render_pentominoes() {
+        let tiles = crate::catalog::find("pentominoes").unwrap().get_tiles();
+        // The I and X pentominoes, in their first layouts.
+        let steps = [(1, 0, 0), (9, 0, 1)]
+            .into_iter()
+            .map(|(tile_index, dx, dy)| Operation {
+                indexed_tile_layout: IndexedTileLayout {
+                    tile_index,
+                    layout_index: 0,
+                },
+                dx,
+                dy,
+            })
+            .collect::<Vec<_>>();
+        let board = "XXXXX\nXXX··\nXXX··\nXXX··".parse::<Board>().unwrap();
+        let drawing = render_solution(&board, &steps, tiles);
+        assert_eq!(drawing, "IIIII\n_X_··\nXXX··\n_X_··");
+        assert_eq!(parse_solution(&drawing, tiles), Ok((board, steps)));
+
+        // Any solution uses the letters of the pentominoes.
+        let mut counts = vec![crate::tile::Count::Limited(0); tiles.len()];
+        for tile_index in [0, 2, 6, 7] {
+            counts[tile_index] = crate::tile::Count::Limited(1);
+        }
+        let board = "XXXXX\nXXXXX\nXXXXX\nXXXXX".parse::<Board>().unwrap();
+        let mut state = search::State::with_counts(board.clone(), tiles, counts);
+        let solution = state.solutions(10_000).next().unwrap();
+        let mut letters = render_solution(&board, &solution, tiles)
+            .chars()
+            .filter(|&c| c != '\n')
+            .collect::<Vec<_>>();
+        letters.sort_unstable();
+        letters.dedup();
+        assert_eq!(letters, vec!['F', 'L', 'U', 'V']);
+    }
+
+    #[test]
+    fn test_touching_copies() {
+        // Two squares side by side, drawn as a single group.
+        let tiles = vec![ALL_TILES[0].clone()];
+        let board = "XXXX\nXXXX".parse::<Board>().unwrap();
+        let steps = vec![
+            Operation {
+                indexed_tile_layout: IndexedTileLayout {
+                    tile_index: 0,
+                    layout_index: 0,
+                },
+                dx: 0,
+                dy: 0,
+            },
+            Operation {
+                indexed_tile_layout: IndexedTileLayout {
+                    tile_index: 0,
+                    layout_index: 0,
+                },
+                dx: 2,
+                dy: 0,
+            },
+        ];
+        let drawing = render_solution(&board, &steps, &tiles);
+        assert_eq!(drawing, "AAAA\nAAAA");
+        let (parsed_board, mut parsed_steps) = parse_solution(&drawing, &tiles).unwrap();
+        assert_eq!(parsed_board, board);
+        parsed_steps.sort_by_key(|op| op.dx);
+        assert_eq!(parsed_steps, steps);
+    }
+
+    #[test]
+    fn test_parse_solution_errors() {
+        let tiles = vec![ALL_TILES[1].clone(), ALL_TILES[2].clone()];
+        assert_eq!(
+            parse_solution("BB·\nCCC", &tiles).map(|(_, steps)| steps.len()),
+            Ok(2)
+        );
+        // Two copies of "B", which are not adjacent:
+        assert_eq!(
+            parse_solution("BB·\n··B\n··B", &tiles).map(|(_, steps)| steps.len()),
+            Ok(2)
+        );
+        // Three copies of "B", which touch each other:
+        assert_eq!(
+            parse_solution("BBB\nB·B\n··B", &tiles).map(|(_, steps)| steps.len()),
+            Ok(3)
+        );
+        assert_eq!(
+            parse_solution("BBB", &tiles),
+            Err(ParseError::InvalidPiece('B'))
+        );
+        assert_eq!(
+            parse_solution("BBZ", &tiles),
+            Err(ParseError::UnexpectedCharacter('Z'))
+        );
+    }
+}
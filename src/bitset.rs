@@ -0,0 +1,147 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign};
+
+// Fixed capacity, so that cloning a Board stays a plain memcpy without any allocation.
+const WORDS: usize = 4;
+type Word = u64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BitSet {
+    words: [Word; WORDS],
+}
+
+impl BitSet {
+    pub const CAPACITY: u32 = Word::BITS * WORDS as u32;
+
+    pub fn new() -> BitSet {
+        BitSet::default()
+    }
+
+    fn locate(index: u32) -> (usize, Word) {
+        assert!(index < Self::CAPACITY);
+        ((index / Word::BITS) as usize, 1 << (index % Word::BITS))
+    }
+
+    pub fn is_set(&self, index: u32) -> bool {
+        let (word_index, mask) = Self::locate(index);
+        0 != self.words[word_index] & mask
+    }
+
+    pub fn set(&mut self, index: u32) {
+        let (word_index, mask) = Self::locate(index);
+        self.words[word_index] |= mask;
+    }
+
+    pub fn clear(&mut self, index: u32) {
+        let (word_index, mask) = Self::locate(index);
+        self.words[word_index] &= !mask;
+    }
+
+    pub fn count_ones(&self) -> u32 {
+        self.words.iter().map(|w| w.count_ones()).sum()
+    }
+}
+
+impl BitAnd for BitSet {
+    type Output = BitSet;
+
+    fn bitand(mut self, rhs: BitSet) -> BitSet {
+        self &= rhs;
+        self
+    }
+}
+
+impl BitAndAssign for BitSet {
+    fn bitand_assign(&mut self, rhs: BitSet) {
+        for (lhs, rhs) in self.words.iter_mut().zip(rhs.words) {
+            *lhs &= rhs;
+        }
+    }
+}
+
+impl BitOr for BitSet {
+    type Output = BitSet;
+
+    fn bitor(mut self, rhs: BitSet) -> BitSet {
+        self |= rhs;
+        self
+    }
+}
+
+impl BitOrAssign for BitSet {
+    fn bitor_assign(&mut self, rhs: BitSet) {
+        for (lhs, rhs) in self.words.iter_mut().zip(rhs.words) {
+            *lhs |= rhs;
+        }
+    }
+}
+
+impl BitXor for BitSet {
+    type Output = BitSet;
+
+    fn bitxor(mut self, rhs: BitSet) -> BitSet {
+        self ^= rhs;
+        self
+    }
+}
+
+impl BitXorAssign for BitSet {
+    fn bitxor_assign(&mut self, rhs: BitSet) {
+        for (lhs, rhs) in self.words.iter_mut().zip(rhs.words) {
+            *lhs ^= rhs;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic_empty() {
+        let b = BitSet::new();
+        assert_eq!(b.count_ones(), 0);
+        for i in 0..BitSet::CAPACITY {
+            assert!(!b.is_set(i));
+        }
+    }
+
+    #[test]
+    fn test_set_clear_across_words() {
+        let mut b = BitSet::new();
+        b.set(0);
+        b.set(63);
+        b.set(64);
+        b.set(BitSet::CAPACITY - 1);
+        assert_eq!(b.count_ones(), 4);
+        assert!(b.is_set(63));
+        assert!(b.is_set(64));
+        assert!(!b.is_set(65));
+        b.clear(63);
+        b.clear(63);
+        assert_eq!(b.count_ones(), 3);
+        assert!(!b.is_set(63));
+        assert!(b.is_set(64));
+    }
+
+    #[test]
+    fn test_and_or_xor() {
+        let mut a = BitSet::new();
+        a.set(1);
+        a.set(100);
+        let mut b = BitSet::new();
+        b.set(100);
+        b.set(200);
+        assert_eq!((a & b).count_ones(), 1);
+        assert!((a & b).is_set(100));
+        assert_eq!((a | b).count_ones(), 3);
+        assert!((a | b).is_set(200));
+        assert_eq!((a ^ b).count_ones(), 2);
+        assert!(!(a ^ b).is_set(100));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_out_of_range() {
+        BitSet::new().set(BitSet::CAPACITY);
+    }
+}
@@ -1,55 +1,153 @@
+use crate::ascii::{self, ParseError};
+use crate::bitset::BitSet;
 use crate::tile;
+use std::fmt;
+use std::str::FromStr;
 
-// This can be changed easily, as long as MAX_WIDTH * MAX_HEIGHT <= 64.
-pub const MAX_WIDTH: u8 = 5;
-pub const MAX_HEIGHT: u8 = 6;
-type BitType = u32;
-const_assert!(BitType::BITS > (MAX_WIDTH * MAX_HEIGHT) as u32);
+// Boards can have any dimensions, as long as they fit into a single BitSet.
+pub const MAX_CELLS: u32 = BitSet::CAPACITY;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Board {
-    bit_data: BitType,
+    width: u8,
+    height: u8,
+    bit_data: BitSet,
 }
 
 impl Board {
-    #[cfg(test)]
-    pub fn all_blocked() -> Board {
-        Board { bit_data: 0 }
+    pub fn all_blocked(width: u8, height: u8) -> Board {
+        assert!(width > 0 && height > 0);
+        assert!(u32::from(width) * u32::from(height) <= MAX_CELLS);
+        Board {
+            width,
+            height,
+            bit_data: BitSet::new(),
+        }
     }
 
-    pub fn from_encoded(encoded: u32) -> Board {
-        let board_size = MAX_WIDTH * MAX_HEIGHT;
-        assert_eq!(0, encoded & !((1 << board_size) - 1));
-        Board { bit_data: encoded }
+    // The cells are numbered row by row, starting at the LSB of the first word.
+    pub fn from_encoded(width: u8, height: u8, encoded: &[u32]) -> Board {
+        let mut board = Board::all_blocked(width, height);
+        let board_size = u32::from(width) * u32::from(height);
+        assert!(encoded.len() as u32 * u32::BITS < board_size + u32::BITS);
+        for (word_index, &word) in encoded.iter().enumerate() {
+            for bit in 0..u32::BITS {
+                if 0 != word & (1 << bit) {
+                    let index = word_index as u32 * u32::BITS + bit;
+                    assert!(index < board_size);
+                    board.bit_data.set(index);
+                }
+            }
+        }
+        board
     }
 
-    fn index_mask(x: u8, y: u8) -> BitType {
-        assert!(x < MAX_WIDTH && y < MAX_HEIGHT);
-        let index = x + MAX_WIDTH * y;
-        assert!(u32::from(index) < BitType::BITS); // Shifting a 1u32 by 32 bits does something other than you think!
-        1 << index
+    // Inverse of 'from_encoded'.
+    pub fn to_encoded(&self) -> Vec<u32> {
+        let board_size = self.count_cells() as u32;
+        let mut encoded = vec![0; board_size.div_ceil(u32::BITS) as usize];
+        for index in 0..board_size {
+            if self.bit_data.is_set(index) {
+                encoded[(index / u32::BITS) as usize] |= 1 << (index % u32::BITS);
+            }
+        }
+        encoded
+    }
+
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    pub fn count_cells(&self) -> usize {
+        usize::from(self.width) * usize::from(self.height)
+    }
+
+    fn index(&self, x: u8, y: u8) -> u32 {
+        assert!(x < self.width && y < self.height);
+        u32::from(x) + u32::from(self.width) * u32::from(y)
     }
 
     pub fn count_unblocked(&self) -> u32 {
         self.bit_data.count_ones()
     }
 
-    #[cfg(test)]
-    pub fn index_mask_for_test(x: u8, y: u8) -> BitType {
-        Self::index_mask(x, y)
+    pub fn is_blocked_at(&self, x: u8, y: u8) -> bool {
+        !self.bit_data.is_set(self.index(x, y))
     }
 
-    pub fn is_blocked_at(&self, x: u8, y: u8) -> bool {
-        0 == (self.bit_data & Self::index_mask(x, y))
+    // Row by row, starting at the top-left.
+    pub fn first_unblocked(&self) -> Option<(u8, u8)> {
+        (0..self.height)
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .find(|&(x, y)| !self.is_blocked_at(x, y))
+    }
+
+    // The sizes of the edge-connected groups of unblocked cells, in no particular order.
+    pub fn region_sizes(&self) -> Vec<u32> {
+        let mut unvisited = self.clone();
+        let mut sizes = vec![];
+        let mut stack = vec![];
+        while let Some(start) = unvisited.first_unblocked() {
+            unvisited.set_blocked(start.0, start.1);
+            stack.push(start);
+            let mut size = 0;
+            while let Some((x, y)) = stack.pop() {
+                size += 1;
+                let neighbours = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    if nx < self.width && ny < self.height && !unvisited.is_blocked_at(nx, ny) {
+                        unvisited.set_blocked(nx, ny);
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+            sizes.push(size);
+        }
+        sizes
     }
 
     pub fn set_blocked(&mut self, x: u8, y: u8) {
-        self.bit_data &= !Self::index_mask(x, y);
+        self.bit_data.clear(self.index(x, y));
     }
 
-    #[cfg(test)]
     pub fn set_unblocked(&mut self, x: u8, y: u8) {
-        self.bit_data |= Self::index_mask(x, y);
+        self.bit_data.set(self.index(x, y));
+    }
+
+    // The cells covered by 'tile_layout' at the given offset, on an otherwise blocked board of
+    // the same dimensions. None if the tile would stick out of the board.
+    pub fn tile_mask(&self, tile_layout: &tile::TileLayout, dx: u8, dy: u8) -> Option<Board> {
+        let mut mask = Board::all_blocked(self.width, self.height);
+        for (x, y) in tile_layout.cells() {
+            let abs_x = dx + x;
+            let abs_y = dy + y;
+            if abs_x >= self.width || abs_y >= self.height {
+                return None;
+            }
+            mask.set_unblocked(abs_x, abs_y);
+        }
+        Some(mask)
+    }
+
+    // Whether all unblocked cells of 'mask' are unblocked here, too.
+    pub fn contains(&self, mask: &Board) -> bool {
+        debug_assert!(self.width == mask.width && self.height == mask.height);
+        self.bit_data & mask.bit_data == mask.bit_data
+    }
+
+    // Blocks all unblocked cells of 'mask'.
+    pub fn block(&mut self, mask: &Board) {
+        debug_assert!(self.width == mask.width && self.height == mask.height);
+        self.bit_data ^= self.bit_data & mask.bit_data;
     }
 
     pub fn with_blocked_tile(
@@ -58,25 +156,61 @@ impl Board {
         dx: u8,
         dy: u8,
     ) -> Option<Board> {
-        assert!(dx < MAX_WIDTH && dy < MAX_HEIGHT);
+        assert!(dx < self.width && dy < self.height);
+        let mask = self.tile_mask(tile_layout, dx, dy)?;
+        if !self.contains(&mask) {
+            // Impossible, abort.
+            return None;
+        }
         let mut result = self.clone();
-        for y in 0..tile::MAX_SIZE {
-            for x in 0..tile::MAX_SIZE {
-                if !tile_layout.is_present_at(x, y) {
-                    continue;
-                }
-                // Try to remove from that position:
-                let abs_x = dx + x;
-                let abs_y = dy + y;
-                if abs_x >= MAX_WIDTH || abs_y >= MAX_HEIGHT || self.is_blocked_at(abs_x, abs_y) {
-                    // Impossible, abort.
-                    return None;
-                }
-                result.set_blocked(abs_x, abs_y);
+        result.block(&mask);
+        Some(result)
+    }
+}
+
+// Row by row, with 'ascii::PRESENT' for each unblocked cell.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for x in 0..self.width {
+                let c = if self.is_blocked_at(x, y) {
+                    ascii::ABSENT
+                } else {
+                    ascii::PRESENT
+                };
+                write!(f, "{}", c)?;
             }
         }
+        Ok(())
+    }
+}
 
-        Some(result)
+impl FromStr for Board {
+    type Err = ParseError;
+
+    fn from_str(drawing: &str) -> Result<Board, ParseError> {
+        let grid = ascii::parse_grid(drawing)?;
+        let (width, height) = (grid[0].len(), grid.len());
+        if width > usize::from(u8::MAX)
+            || height > usize::from(u8::MAX)
+            || width * height > MAX_CELLS as usize
+        {
+            return Err(ParseError::TooLarge);
+        }
+        let mut board = Board::all_blocked(width as u8, height as u8);
+        for (y, row) in grid.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                if c == ascii::PRESENT {
+                    board.set_unblocked(x as u8, y as u8);
+                } else if !ascii::is_absent(c) {
+                    return Err(ParseError::UnexpectedCharacter(c));
+                }
+            }
+        }
+        Ok(board)
     }
 }
 
@@ -84,55 +218,76 @@ impl Board {
 mod tests {
     use super::*;
 
+    fn low_bits(b: &Board) -> u64 {
+        (0..64)
+            .filter(|&i| i < b.count_cells() as u32 && b.bit_data.is_set(i))
+            .map(|i| 1 << i)
+            .sum()
+    }
+
+    fn index_mask(b: &Board, x: u8, y: u8) -> u64 {
+        1 << b.index(x, y)
+    }
+
     #[test]
     fn test_basic_all_blocked() {
-        let b = Board::all_blocked();
-        assert_eq!(b.bit_data, 0);
+        let b = Board::all_blocked(5, 6);
+        assert_eq!(low_bits(&b), 0);
     }
 
     #[test]
     fn test_basic_blocked_idempotency() {
-        let mut b = Board::all_blocked();
+        let mut b = Board::all_blocked(5, 6);
         b.set_blocked(0, 0);
-        assert_eq!(b.bit_data, 0);
+        assert_eq!(low_bits(&b), 0);
     }
 
     #[test]
     fn test_basic_set_unblocked() {
-        let mut b = Board::all_blocked();
+        let mut b = Board::all_blocked(5, 6);
         b.set_unblocked(0, 0);
-        assert_eq!(b.bit_data, 1);
+        assert_eq!(low_bits(&b), 1);
         b.set_unblocked(0, 2);
-        // This assumes MAX_WIDTH == 5:
+        // This assumes a width of 5:
         let mask_0_2 = 0x400;
-        assert_eq!(b.bit_data, 1 + mask_0_2);
+        assert_eq!(low_bits(&b), 1 + mask_0_2);
         // Test idempotency
         b.set_unblocked(0, 2);
-        assert_eq!(b.bit_data, 1 + mask_0_2);
+        assert_eq!(low_bits(&b), 1 + mask_0_2);
         // Test set_blocked
         b.set_blocked(0, 0);
-        assert_eq!(b.bit_data, mask_0_2);
+        assert_eq!(low_bits(&b), mask_0_2);
     }
 
     #[test]
     fn test_index_mask() {
-        assert_eq!(Board::index_mask_for_test(0, 0), 0x00000001);
-        assert_eq!(Board::index_mask_for_test(1, 0), 0x00000002);
-        assert_eq!(Board::index_mask_for_test(2, 0), 0x00000004);
-        assert_eq!(Board::index_mask_for_test(3, 0), 0x00000008);
-        assert_eq!(Board::index_mask_for_test(4, 0), 0x00000010);
-        assert_eq!(Board::index_mask_for_test(0, 1), 0x00000020);
-        assert_eq!(Board::index_mask_for_test(1, 1), 0x00000040);
-        assert_eq!(Board::index_mask_for_test(2, 1), 0x00000080);
-        assert_eq!(Board::index_mask_for_test(3, 1), 0x00000100);
-        assert_eq!(Board::index_mask_for_test(4, 1), 0x00000200);
-        assert_eq!(Board::index_mask_for_test(0, 2), 0x00000400);
-        assert_eq!(Board::index_mask_for_test(0, 3), 0x00008000);
-        assert_eq!(Board::index_mask_for_test(0, 4), 0x00100000);
-        assert_eq!(Board::index_mask_for_test(1, 4), 0x00200000);
-        assert_eq!(Board::index_mask_for_test(2, 4), 0x00400000);
-        assert_eq!(Board::index_mask_for_test(3, 4), 0x00800000);
-        assert_eq!(Board::index_mask_for_test(4, 4), 0x01000000);
+        let b = Board::all_blocked(5, 6);
+        assert_eq!(index_mask(&b, 0, 0), 0x00000001);
+        assert_eq!(index_mask(&b, 1, 0), 0x00000002);
+        assert_eq!(index_mask(&b, 2, 0), 0x00000004);
+        assert_eq!(index_mask(&b, 3, 0), 0x00000008);
+        assert_eq!(index_mask(&b, 4, 0), 0x00000010);
+        assert_eq!(index_mask(&b, 0, 1), 0x00000020);
+        assert_eq!(index_mask(&b, 1, 1), 0x00000040);
+        assert_eq!(index_mask(&b, 2, 1), 0x00000080);
+        assert_eq!(index_mask(&b, 3, 1), 0x00000100);
+        assert_eq!(index_mask(&b, 4, 1), 0x00000200);
+        assert_eq!(index_mask(&b, 0, 2), 0x00000400);
+        assert_eq!(index_mask(&b, 0, 3), 0x00008000);
+        assert_eq!(index_mask(&b, 0, 4), 0x00100000);
+        assert_eq!(index_mask(&b, 1, 4), 0x00200000);
+        assert_eq!(index_mask(&b, 2, 4), 0x00400000);
+        assert_eq!(index_mask(&b, 3, 4), 0x00800000);
+        assert_eq!(index_mask(&b, 4, 4), 0x01000000);
+    }
+
+    #[test]
+    fn test_index_mask_other_width() {
+        let b = Board::all_blocked(8, 3);
+        assert_eq!(index_mask(&b, 0, 0), 0x00000001);
+        assert_eq!(index_mask(&b, 7, 0), 0x00000080);
+        assert_eq!(index_mask(&b, 0, 1), 0x00000100);
+        assert_eq!(index_mask(&b, 7, 2), 0x00800000);
     }
 
     fn tile_from(bits: u16) -> tile::TileLayout {
@@ -141,38 +296,38 @@ mod tests {
 
     #[test]
     fn test_basic_blocked_tile_positive() {
-        let mut b = Board::all_blocked();
+        let mut b = Board::all_blocked(5, 6);
         b.set_unblocked(0, 0);
-        assert_eq!(b.bit_data, 1);
+        assert_eq!(low_bits(&b), 1);
         assert_eq!(
             b.with_blocked_tile(&tile_from(0x0001), 0, 0),
-            Some(Board::all_blocked())
+            Some(Board::all_blocked(5, 6))
         );
     }
 
     #[test]
     fn test_empty_blocked_tile_positive() {
-        let b = Board::all_blocked();
-        assert_eq!(b.bit_data, 0);
+        let b = Board::all_blocked(5, 6);
+        assert_eq!(low_bits(&b), 0);
         assert_eq!(
             b.with_blocked_tile(&tile_from(0x0000), 0, 0),
-            Some(Board::all_blocked())
+            Some(Board::all_blocked(5, 6))
         );
         assert_eq!(
             b.with_blocked_tile(&tile_from(0x0000), 1, 0),
-            Some(Board::all_blocked())
+            Some(Board::all_blocked(5, 6))
         );
         assert_eq!(
             b.with_blocked_tile(&tile_from(0x0000), 3, 3),
-            Some(Board::all_blocked())
+            Some(Board::all_blocked(5, 6))
         );
     }
 
     #[test]
     fn test_basic_blocked_tile_negative() {
-        let mut b = Board::all_blocked();
+        let mut b = Board::all_blocked(5, 6);
         b.set_unblocked(0, 0);
-        assert_eq!(b.bit_data, 1);
+        assert_eq!(low_bits(&b), 1);
         assert_eq!(b.with_blocked_tile(&tile_from(0x0001), 0, 1), None);
         assert_eq!(b.with_blocked_tile(&tile_from(0x0001), 3, 3), None);
         assert_eq!(b.with_blocked_tile(&tile_from(0x0001), 1, 0), None);
@@ -180,13 +335,13 @@ mod tests {
 
     #[test]
     fn test_two_blocked_tile() {
-        let mut b = Board::all_blocked();
+        let mut b = Board::all_blocked(5, 6);
         b.set_unblocked(0, 1);
         b.set_unblocked(0, 2);
         assert_eq!(b.with_blocked_tile(&tile_from(0x0011), 0, 0), None);
         assert_eq!(
             b.with_blocked_tile(&tile_from(0x0011), 0, 1),
-            Some(Board::all_blocked())
+            Some(Board::all_blocked(5, 6))
         );
         assert_eq!(b.with_blocked_tile(&tile_from(0x0011), 0, 2), None);
     }
@@ -199,13 +354,8 @@ mod tests {
         //     XXXXX
         //     ··XXX
         //     ····· MSB
-        let b = Board::from_encoded(0x01CFDC63);
-        for y in 0..MAX_HEIGHT {
-            for x in 0..MAX_WIDTH {
-                print!("{}", if b.is_blocked_at(x, y) { "·" } else { "X" });
-            }
-            println!();
-        }
+        let b = Board::from_encoded(5, 6, &[0x01CFDC63]);
+        println!("{}", b);
         assert!(!b.is_blocked_at(0, 0));
         assert!(!b.is_blocked_at(1, 0));
         assert!(b.is_blocked_at(2, 0));
@@ -237,4 +387,141 @@ mod tests {
         assert!(b.is_blocked_at(3, 5));
         assert!(b.is_blocked_at(4, 5));
     }
+
+    #[test]
+    fn test_from_encoded_multi_word() {
+        // 8×8 board with only the last cell unblocked.
+        let b = Board::from_encoded(8, 8, &[0x00000000, 0x80000000]);
+        assert_eq!(b.count_unblocked(), 1);
+        assert!(!b.is_blocked_at(7, 7));
+        assert!(b.is_blocked_at(6, 7));
+        assert!(b.is_blocked_at(7, 6));
+    }
+
+    #[test]
+    fn test_to_encoded() {
+        assert_eq!(
+            Board::from_encoded(5, 6, &[0x01CFDC63]).to_encoded(),
+            vec![0x01CFDC63]
+        );
+        assert_eq!(
+            Board::from_encoded(8, 8, &[0x00000000, 0x80000000]).to_encoded(),
+            vec![0x00000000, 0x80000000]
+        );
+        assert_eq!(Board::all_blocked(3, 11).to_encoded(), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_blocked_tile_other_dimensions() {
+        // XXXXXXX
+        // XXXXXXX
+        // XXXXXXX
+        // XXXXXXX
+        let b = Board::from_encoded(7, 4, &[0x0FFFFFFF]);
+        assert_eq!(b.count_unblocked(), 28);
+        let b = b.with_blocked_tile(&tile_from(0x000F), 3, 3).unwrap();
+        assert_eq!(b.count_unblocked(), 24);
+        assert!(b.is_blocked_at(6, 3));
+        assert!(!b.is_blocked_at(2, 3));
+        assert_eq!(b.with_blocked_tile(&tile_from(0x000F), 4, 0), None);
+        assert_eq!(b.with_blocked_tile(&tile_from(0x1111), 0, 1), None);
+    }
+
+    #[test]
+    fn test_large_board() {
+        // 12×12 board, only the bottom right 2×2 square is unblocked.
+        let mut encoded = [0u32; 5];
+        for (x, y) in [(10, 10), (11, 10), (10, 11), (11, 11)] {
+            let index = x + 12 * y;
+            encoded[index / 32] |= 1 << (index % 32);
+        }
+        let b = Board::from_encoded(12, 12, &encoded);
+        assert_eq!(b.count_cells(), 144);
+        assert_eq!(b.count_unblocked(), 4);
+        assert!(!b.is_blocked_at(11, 11));
+        assert!(b.is_blocked_at(9, 11));
+        assert_eq!(b.with_blocked_tile(&tile_from(0x0033), 9, 10), None);
+        assert_eq!(
+            b.with_blocked_tile(&tile_from(0x0033), 10, 10),
+            Some(Board::all_blocked(12, 12))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_too_large_board() {
+        Board::all_blocked(17, 16);
+    }
+
+    #[test]
+    fn test_large_tile() {
+        let bar = tile::TileLayout::from_cells(&[(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]);
+        let b = Board::from_encoded(5, 2, &[0x3FF]);
+        assert_eq!(
+            b.with_blocked_tile(&bar, 0, 1).unwrap().count_unblocked(),
+            5
+        );
+        assert_eq!(b.with_blocked_tile(&bar, 1, 0), None);
+        assert_eq!(b.with_blocked_tile(&bar.rotated(), 0, 0), None);
+    }
+
+    #[test]
+    fn test_first_unblocked() {
+        let mut b = Board::all_blocked(5, 6);
+        assert_eq!(b.first_unblocked(), None);
+        b.set_unblocked(4, 5);
+        assert_eq!(b.first_unblocked(), Some((4, 5)));
+        b.set_unblocked(0, 5);
+        b.set_unblocked(3, 2);
+        assert_eq!(b.first_unblocked(), Some((3, 2)));
+    }
+
+    #[test]
+    fn test_masks() {
+        let mut b: Board = "XXX\nX.X".parse().unwrap();
+        let bar = tile_from(0x0003);
+        assert_eq!(b.tile_mask(&bar, 2, 0), None);
+        let mask = b.tile_mask(&bar, 1, 0).unwrap();
+        assert_eq!(mask.to_string(), "·XX\n···");
+        assert!(b.contains(&mask));
+        assert!(!b.contains(&b.tile_mask(&bar, 1, 1).unwrap()));
+        b.block(&mask);
+        assert_eq!(b.to_string(), "X··\nX·X");
+        assert!(!b.contains(&mask));
+        // Blocking is idempotent.
+        b.block(&mask);
+        assert_eq!(b.to_string(), "X··\nX·X");
+    }
+
+    #[test]
+    fn test_region_sizes() {
+        assert_eq!(Board::all_blocked(3, 2).region_sizes(), Vec::<u32>::new());
+        let board: Board = "XX.X\nX..X\n..XX\nX..X".parse().unwrap();
+        // Diagonal neighbours are not connected.
+        assert_eq!(board.region_sizes(), vec![3, 5, 1]);
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        // Same as in test_from_encoded.
+        let b = Board::from_encoded(5, 6, &[0x01CFDC63]);
+        let drawing = "XX···\nXX···\nXXX·X\nXXXXX\n··XXX\n·····";
+        assert_eq!(b.to_string(), drawing);
+        assert_eq!(drawing.parse(), Ok(b));
+        assert_eq!(
+            "
+            ..X
+            X..
+            "
+            .parse::<Board>()
+            .map(|b| b.count_unblocked()),
+            Ok(2)
+        );
+        assert_eq!("X·\nX".parse::<Board>(), Err(ParseError::UnevenRows));
+        assert_eq!(
+            "X·\nXO".parse::<Board>(),
+            Err(ParseError::UnexpectedCharacter('O'))
+        );
+        assert_eq!("X".repeat(257).parse::<Board>(), Err(ParseError::TooLarge));
+    }
 }
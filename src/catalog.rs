@@ -0,0 +1,211 @@
+use crate::tile::{Tile, TileLayout, ALL_TILES, MAX_SIZE};
+use std::collections::BTreeSet;
+
+// The tiles are painted into the cells of a solution by their index, and 254 and 255 are
+// already taken, see 'lib.rs'.
+pub const MAX_TILES: usize = 254;
+
+// A named set of tiles, usually all the pieces of one physical game.
+#[derive(Debug)]
+pub struct Catalog {
+    name: &'static str,
+    tiles: Vec<Tile>,
+}
+
+impl Catalog {
+    fn new(name: &'static str, tiles: Vec<Tile>) -> Catalog {
+        assert!(tiles.len() <= MAX_TILES);
+        Catalog { name, tiles }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get_tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+}
+
+pub fn find(name: &str) -> Option<&'static Catalog> {
+    CATALOGS.iter().find(|catalog| catalog.name == name)
+}
+
+fn from_rows(name: &str, rows: &[u8]) -> Tile {
+    Tile::from_shape(&TileLayout::from_rows(rows)).named(name)
+}
+
+// The smallest of all rotations and reflections, to compare shapes regardless of their layout.
+fn canonical(layout: &TileLayout) -> TileLayout {
+    Tile::from_shape(layout)
+        .get_layouts()
+        .iter()
+        .min()
+        .unwrap()
+        .clone()
+}
+
+// All polyominoes with the given number of cells, counting rotations and reflections only once.
+// Each is given by its canonical layout, in ascending order.
+pub fn free_polyominoes(size: u8) -> Vec<TileLayout> {
+    // Growing a shape may extend it by one cell in each direction:
+    assert!(size > 0 && size < MAX_SIZE);
+    let mut current = vec![TileLayout::from_cells(&[(0, 0)])];
+    for _ in 1..size {
+        let mut next = BTreeSet::new();
+        for layout in &current {
+            // Make room to the top and left, so that 'x - 1' and 'y - 1' cannot underflow.
+            let cells = layout
+                .cells()
+                .map(|(x, y)| (x + 1, y + 1))
+                .collect::<Vec<_>>();
+            for &(x, y) in &cells {
+                for neighbor in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if cells.contains(&neighbor) {
+                        continue;
+                    }
+                    let mut grown = cells.clone();
+                    grown.push(neighbor);
+                    next.insert(canonical(&TileLayout::from_cells(&grown)));
+                }
+            }
+        }
+        current = next.into_iter().collect();
+    }
+    current
+}
+
+// The pieces of the children's edition are still missing, see the TODOs in the README.
+lazy_static! {
+    pub static ref CATALOGS: Vec<Catalog> = vec![
+        Catalog::new("ubongo", ALL_TILES.clone()),
+        /*
+         * I: XXXX
+         *
+         * O: XX   T: XXX   S: ·XX   L: X·
+         *    XX      ·X·      XX·      X·
+         *                              XX
+         */
+        Catalog::new(
+            "tetrominoes",
+            vec![
+                from_rows("I", &[0x0F]),
+                from_rows("O", &[0x03, 0x03]),
+                from_rows("T", &[0x07, 0x02]),
+                from_rows("S", &[0x06, 0x03]),
+                from_rows("L", &[0x01, 0x01, 0x03]),
+            ]
+        ),
+        /*
+         * F: ·XX   I: XXXXX   L: X·   N: ·X   P: XX   T: XXX
+         *    XX·                 X·      XX      XX      ·X·
+         *    ·X·                 X·      X·      X·      ·X·
+         *                        XX      X·
+         *
+         * U: X·X   V: X··     W: X··  X: ·X·  Y: ·X   Z: XX·
+         *    XXX      X··        XX·     XXX     XX      ·X·
+         *             XXX        ·XX     ·X·     ·X      ·XX
+         *                                        ·X
+         */
+        Catalog::new(
+            "pentominoes",
+            vec![
+                from_rows("F", &[0x06, 0x03, 0x02]),
+                from_rows("I", &[0x1F]),
+                from_rows("L", &[0x01, 0x01, 0x01, 0x03]),
+                from_rows("N", &[0x02, 0x03, 0x01, 0x01]),
+                from_rows("P", &[0x03, 0x03, 0x01]),
+                from_rows("T", &[0x07, 0x02, 0x02]),
+                from_rows("U", &[0x05, 0x07]),
+                from_rows("V", &[0x01, 0x01, 0x07]),
+                from_rows("W", &[0x01, 0x03, 0x06]),
+                from_rows("X", &[0x02, 0x07, 0x02]),
+                from_rows("Y", &[0x02, 0x03, 0x02, 0x02]),
+                from_rows("Z", &[0x03, 0x02, 0x06]),
+            ]
+        ),
+        // There are no commonly used letters for hexominoes, so they are simply numbered, and
+        // continue with letters after 9. This keeps each name a single character for drawings.
+        // 'X' is left out, since it stands for a cell in drawings of boards.
+        Catalog::new(
+            "hexominoes",
+            free_polyominoes(6)
+                .iter()
+                .zip("0123456789ABCDEFGHIJKLMNOPQRSTUVWYZ".chars())
+                .map(|(shape, name)| Tile::from_shape(shape).named(&name.to_string()))
+                .collect()
+        ),
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn canonical_shapes(catalog: &Catalog) -> BTreeSet<TileLayout> {
+        catalog
+            .get_tiles()
+            .iter()
+            .map(|tile| canonical(&tile.get_layouts()[0]))
+            .collect()
+    }
+
+    #[test]
+    fn test_free_polyominoes_counts() {
+        let counts = (1..=6)
+            .map(|size| free_polyominoes(size).len())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![1, 1, 2, 5, 12, 35]);
+        for layout in free_polyominoes(5) {
+            assert_eq!(layout.check_shape(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_catalogs_are_complete() {
+        let tetrominoes = find("tetrominoes").unwrap();
+        assert_eq!(
+            canonical_shapes(tetrominoes),
+            free_polyominoes(4).into_iter().collect()
+        );
+        let pentominoes = find("pentominoes").unwrap();
+        assert_eq!(
+            canonical_shapes(pentominoes),
+            free_polyominoes(5).into_iter().collect()
+        );
+        assert_eq!(find("hexominoes").unwrap().get_tiles().len(), 35);
+        assert_eq!(find("ubongo").unwrap().get_tiles().len(), ALL_TILES.len());
+        assert!(find("nonexistent").is_none());
+    }
+
+    #[test]
+    fn test_names_are_unique() {
+        let catalog_names = CATALOGS
+            .iter()
+            .map(|c| c.get_name())
+            .collect::<HashSet<_>>();
+        assert_eq!(catalog_names.len(), CATALOGS.len());
+        for catalog in CATALOGS.iter() {
+            let tile_names = catalog
+                .get_tiles()
+                .iter()
+                .map(|tile| tile.get_name())
+                .collect::<HashSet<_>>();
+            assert_eq!(tile_names.len(), catalog.get_tiles().len());
+            assert!(tile_names.iter().all(|name| name.chars().count() == 1));
+            // Drawings show the names, not some fallback symbols.
+            let symbols = crate::ascii::tile_symbols(catalog.get_tiles());
+            for (tile, symbol) in catalog.get_tiles().iter().zip(symbols) {
+                assert_eq!(tile.get_name(), symbol.to_string(), "{}", catalog.get_name());
+            }
+        }
+        let ubongo_names = find("ubongo")
+            .unwrap()
+            .get_tiles()
+            .iter()
+            .map(|tile| tile.get_name())
+            .collect::<String>();
+        assert_eq!(ubongo_names, "ABCDEFGHJKLM");
+    }
+}
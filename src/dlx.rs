@@ -0,0 +1,439 @@
+use crate::board::Board;
+use crate::placement_table::PlacementTable;
+use crate::search::{self, IndexedTileLayout, Operation};
+use crate::tile::{Count, Tile};
+
+/* Alternative to 'search::State': Knuth's Algorithm X with Dancing Links.
+ *
+ * Each possible placement of a tile is one row of an exact-cover matrix. The columns are the
+ * unblocked cells of the board, and one column for each tile with a limited count. Tile columns
+ * must be covered exactly 'count' times. Cells are covered at most once; they must be covered
+ * exactly once only if some tile has an unlimited supply, just like in 'search::State'.
+ * Columns that need not be covered are "secondary", i.e. they are not linked into the root.
+ *
+ * To avoid enumerating the same set of rows in different orders when a tile column has to be
+ * covered several times, a row is hidden from its siblings after its subtree has been explored.
+ */
+
+const ROOT: usize = 0;
+
+#[derive(Debug, PartialEq)]
+pub struct Solutions {
+    // Only the first solution is kept, so that counting takes constant memory.
+    pub first: Option<search::Result>,
+    pub num_solutions: usize,
+    pub steps_taken: usize,
+    // The whole tree was explored, so 'num_solutions' is exact.
+    pub has_finished: bool,
+}
+
+#[derive(Debug)]
+struct Matrix {
+    // Node 0 is the root, followed by the column headers, followed by the rows.
+    left: Vec<usize>,
+    right: Vec<usize>,
+    up: Vec<usize>,
+    down: Vec<usize>,
+    column: Vec<usize>,
+    // Only meaningful for column headers:
+    size: Vec<usize>,
+    capacity: Vec<usize>,
+    // Only meaningful for row nodes: Index into 'operations'.
+    row: Vec<usize>,
+    operations: Vec<Operation>,
+}
+
+impl Matrix {
+    // 'is_primary' and 'capacities' are parallel, one entry per column.
+    fn new(is_primary: &[bool], capacities: &[usize]) -> Matrix {
+        let num_headers = 1 + is_primary.len();
+        let mut matrix = Matrix {
+            left: (0..num_headers).collect(),
+            right: (0..num_headers).collect(),
+            up: (0..num_headers).collect(),
+            down: (0..num_headers).collect(),
+            column: (0..num_headers).collect(),
+            size: vec![0; num_headers],
+            capacity: [0].iter().chain(capacities).copied().collect(),
+            row: vec![usize::MAX; num_headers],
+            operations: Vec::new(),
+        };
+        for (index, _) in is_primary.iter().enumerate().filter(|(_, &p)| p) {
+            let header = index + 1;
+            let last = matrix.left[ROOT];
+            matrix.left[header] = last;
+            matrix.right[header] = ROOT;
+            matrix.right[last] = header;
+            matrix.left[ROOT] = header;
+        }
+        matrix
+    }
+
+    // 'columns' are 0-based, i.e. without the root.
+    fn add_row(&mut self, columns: &[usize], operation: Operation) {
+        let row = self.operations.len();
+        self.operations.push(operation);
+        let first = self.left.len();
+        for (i, &column) in columns.iter().enumerate() {
+            let node = first + i;
+            let header = column + 1;
+            let last = if i + 1 == columns.len() {
+                first
+            } else {
+                node + 1
+            };
+            let previous = if i == 0 {
+                first + columns.len() - 1
+            } else {
+                node - 1
+            };
+            self.left.push(previous);
+            self.right.push(last);
+            self.up.push(self.up[header]);
+            self.down.push(header);
+            self.column.push(header);
+            self.row.push(row);
+            let above = self.up[header];
+            self.down[above] = node;
+            self.up[header] = node;
+            self.size[header] += 1;
+        }
+    }
+
+    fn unlink_vertically(&mut self, node: usize) {
+        let (up, down) = (self.up[node], self.down[node]);
+        self.down[up] = down;
+        self.up[down] = up;
+        self.size[self.column[node]] -= 1;
+    }
+
+    fn relink_vertically(&mut self, node: usize) {
+        let (up, down) = (self.up[node], self.down[node]);
+        self.down[up] = node;
+        self.up[down] = node;
+        self.size[self.column[node]] += 1;
+    }
+
+    fn cover(&mut self, header: usize) {
+        let (left, right) = (self.left[header], self.right[header]);
+        self.right[left] = right;
+        self.left[right] = left;
+        let mut i = self.down[header];
+        while i != header {
+            let mut j = self.right[i];
+            while j != i {
+                self.unlink_vertically(j);
+                j = self.right[j];
+            }
+            i = self.down[i];
+        }
+    }
+
+    fn uncover(&mut self, header: usize) {
+        let mut i = self.up[header];
+        while i != header {
+            let mut j = self.left[i];
+            while j != i {
+                self.relink_vertically(j);
+                j = self.left[j];
+            }
+            i = self.up[i];
+        }
+        let (left, right) = (self.left[header], self.right[header]);
+        self.right[left] = header;
+        self.left[right] = header;
+    }
+
+    // Covering a column happens only once its capacity is used up.
+    fn select(&mut self, node: usize) {
+        let mut j = node;
+        loop {
+            let header = self.column[j];
+            self.capacity[header] -= 1;
+            if self.capacity[header] == 0 {
+                self.cover(header);
+            }
+            j = self.right[j];
+            if j == node {
+                break;
+            }
+        }
+    }
+
+    // Exactly reverses 'select'.
+    fn unselect(&mut self, node: usize) {
+        let mut j = node;
+        loop {
+            j = self.left[j];
+            let header = self.column[j];
+            if self.capacity[header] == 0 {
+                self.uncover(header);
+            }
+            self.capacity[header] += 1;
+            if j == node {
+                break;
+            }
+        }
+    }
+
+    fn hide_row(&mut self, node: usize) {
+        let mut j = node;
+        loop {
+            self.unlink_vertically(j);
+            j = self.right[j];
+            if j == node {
+                break;
+            }
+        }
+    }
+
+    fn unhide_row(&mut self, node: usize) {
+        let mut j = node;
+        loop {
+            j = self.left[j];
+            self.relink_vertically(j);
+            if j == node {
+                break;
+            }
+        }
+    }
+
+    // The primary column with the fewest rows, or None if all of them are covered.
+    fn choose_column(&self) -> Option<usize> {
+        let mut best = None;
+        let mut header = self.right[ROOT];
+        while header != ROOT {
+            if best.is_none_or(|best| self.size[header] < self.size[best]) {
+                best = Some(header);
+            }
+            header = self.right[header];
+        }
+        best
+    }
+}
+
+struct Search<'f> {
+    matrix: Matrix,
+    selected: Vec<usize>,
+    max_steps: usize,
+    max_solutions: usize,
+    solutions: Solutions,
+    on_solution: &'f mut dyn FnMut(&search::Result),
+}
+
+impl Search<'_> {
+    // Returns true if the search must stop, i.e. when the budget is used up.
+    fn explore(&mut self) -> bool {
+        let Some(header) = self.matrix.choose_column() else {
+            // Leaf first, like 'search::State'.
+            let result = self
+                .selected
+                .iter()
+                .rev()
+                .map(|&node| self.matrix.operations[self.matrix.row[node]].clone())
+                .collect();
+            (self.on_solution)(&result);
+            self.solutions.first.get_or_insert(result);
+            self.solutions.num_solutions += 1;
+            return self.solutions.num_solutions >= self.max_solutions;
+        };
+        if self.solutions.steps_taken >= self.max_steps {
+            return true;
+        }
+        self.solutions.steps_taken += 1;
+        if self.matrix.size[header] < self.matrix.capacity[header] {
+            return false;
+        }
+        let mut hidden = Vec::new();
+        let mut stop = false;
+        let mut node = self.matrix.down[header];
+        while node != header && !stop {
+            self.matrix.select(node);
+            self.selected.push(node);
+            stop = self.explore();
+            self.selected.pop();
+            self.matrix.unselect(node);
+            // All solutions containing this row have been found now.
+            self.matrix.hide_row(node);
+            hidden.push(node);
+            node = self.matrix.down[node];
+        }
+        for &node in hidden.iter().rev() {
+            self.matrix.unhide_row(node);
+        }
+        stop
+    }
+}
+
+// Finds up to 'max_solutions' solutions of the same problem as 'search::State::with_counts'.
+// The tiles must not be empty.
+pub fn solve(
+    board: &Board,
+    tiles: &[Tile],
+    counts: &[Count],
+    max_steps: usize,
+    max_solutions: usize,
+) -> Solutions {
+    solve_with(board, tiles, counts, max_steps, max_solutions, &mut |_| {})
+}
+
+// Same as 'solve', but also calls 'on_solution' for each solution as it is found.
+pub fn solve_with(
+    board: &Board,
+    tiles: &[Tile],
+    counts: &[Count],
+    max_steps: usize,
+    max_solutions: usize,
+    on_solution: &mut dyn FnMut(&search::Result),
+) -> Solutions {
+    assert_eq!(tiles.len(), counts.len());
+    let must_fill_board = counts.contains(&Count::Unlimited);
+    let mut cell_columns = vec![usize::MAX; board.count_cells()];
+    let mut is_primary = Vec::new();
+    let mut capacities = Vec::new();
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            if !board.is_blocked_at(x, y) {
+                cell_columns[usize::from(y) * usize::from(board.width()) + usize::from(x)] =
+                    is_primary.len();
+                is_primary.push(must_fill_board);
+                capacities.push(1);
+            }
+        }
+    }
+    let mut tile_columns = vec![None; tiles.len()];
+    for (tile_index, &count) in counts.iter().enumerate() {
+        if let Count::Limited(count @ 1..) = count {
+            tile_columns[tile_index] = Some(is_primary.len());
+            is_primary.push(true);
+            capacities.push(usize::from(count));
+        }
+    }
+
+    let mut matrix = Matrix::new(&is_primary, &capacities);
+    let placement_table = PlacementTable::new(board, tiles);
+    for (tile_index, tile) in tiles.iter().enumerate() {
+        if counts[tile_index] == Count::Limited(0) {
+            continue;
+        }
+        for fit in placement_table.fits(tile_index) {
+            let layout = &tile.get_layouts()[usize::from(fit.layout_index)];
+            debug_assert!(layout.cells().next().is_some());
+            let mut columns = tile_columns[tile_index].into_iter().collect::<Vec<_>>();
+            columns.extend(layout.cells().map(|(x, y)| {
+                let (x, y) = (usize::from(x + fit.dx), usize::from(y + fit.dy));
+                cell_columns[y * usize::from(board.width()) + x]
+            }));
+            let operation = Operation {
+                indexed_tile_layout: IndexedTileLayout {
+                    tile_index: tile_index as u8,
+                    layout_index: fit.layout_index,
+                },
+                dx: fit.dx,
+                dy: fit.dy,
+            };
+            matrix.add_row(&columns, operation);
+        }
+    }
+
+    let mut search = Search {
+        matrix,
+        selected: Vec::new(),
+        max_steps,
+        max_solutions,
+        solutions: Solutions {
+            first: None,
+            num_solutions: 0,
+            steps_taken: 0,
+            has_finished: false,
+        },
+        on_solution,
+    };
+    search.solutions.has_finished = !search.explore();
+    search.solutions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog;
+    use crate::search::State;
+
+    fn count_both(board: &Board, tiles: &[Tile], counts: Vec<Count>) -> usize {
+        let mut all = Vec::new();
+        let dlx = solve_with(
+            board,
+            tiles,
+            &counts,
+            1_000_000,
+            usize::MAX,
+            &mut |result| all.push(result.clone()),
+        );
+        assert!(dlx.has_finished);
+        assert_eq!(dlx.num_solutions, all.len());
+        let mut state = State::with_counts(board.clone(), tiles, counts);
+        let mut solutions = state.solutions(1_000_000);
+        let mut dfs = solutions.by_ref().collect::<Vec<_>>();
+        assert!(solutions.has_finished());
+        let mut dlx = all;
+        for result in dfs.iter_mut().chain(dlx.iter_mut()) {
+            result.sort_by_key(|op| format!("{:?}", op));
+        }
+        dfs.sort_by_key(|r| format!("{:?}", r));
+        dlx.sort_by_key(|r| format!("{:?}", r));
+        assert_eq!(dfs, dlx);
+        dlx.len()
+    }
+
+    #[test]
+    fn test_sample_easy() {
+        let board = "·XX··\nXXXX·\n·XXXX\n·XXX·".parse().unwrap();
+        let tiles = catalog::find("ubongo").unwrap().get_tiles();
+        let mut counts = vec![Count::Limited(0); tiles.len()];
+        for i in [5, 6, 10] {
+            counts[i] = Count::Limited(1);
+        }
+        let first = solve(&board, tiles, &counts, 1000, 1);
+        assert_eq!(first.num_solutions, 1);
+        assert!(first.first.is_some());
+        assert!(!first.has_finished);
+        assert_eq!(count_both(&board, tiles, counts), 1);
+    }
+
+    #[test]
+    fn test_copies_and_slack() {
+        // Two copies of the same tromino, and one domino, with one cell to spare.
+        let board = "XXX\nXXX\nXX·".parse().unwrap();
+        let tiles = vec![
+            Tile::from_shape(&"XX\nX·".parse().unwrap()),
+            Tile::from_shape(&"XX".parse().unwrap()),
+        ];
+        let counts = vec![Count::Limited(2), Count::Limited(1)];
+        assert!(count_both(&board, &tiles, counts) > 1);
+    }
+
+    #[test]
+    fn test_unlimited() {
+        let tiles = vec![
+            Tile::from_shape(&"XXX".parse().unwrap()),
+            Tile::from_shape(&"X".parse().unwrap()),
+        ];
+        let board: Board = "XXX\nXXX\nXXX".parse().unwrap();
+        assert_eq!(
+            count_both(&board, &tiles, vec![Count::Limited(1), Count::Unlimited]),
+            6
+        );
+        let dominoes = [Tile::from_shape(&"XX".parse().unwrap())];
+        let board = "XXXXX\nXXXXX".parse().unwrap();
+        assert_eq!(count_both(&board, &dominoes, vec![Count::Unlimited]), 8);
+    }
+
+    #[test]
+    fn test_budget() {
+        let board = "XXXXXX\nXXXXXX\nXXXXXX".parse().unwrap();
+        let tiles = catalog::find("tetrominoes").unwrap().get_tiles();
+        let solutions = solve(&board, tiles, &[Count::Unlimited; 5], 3, usize::MAX);
+        assert_eq!(solutions.steps_taken, 3);
+        assert!(!solutions.has_finished);
+    }
+}
@@ -0,0 +1,381 @@
+//! How the depth-first search decides what to branch on, and which child to explore first.
+//!
+//! The built-in heuristics are listed by [`builtin`]. Use [`compare`] to measure them (or your
+//! own) on a collection of puzzles.
+
+use crate::board::Board;
+use crate::puzzle::Puzzle;
+use crate::search::{Node, Operation};
+use crate::solver::{Placement, Solver};
+use crate::tile::{Tile, TileLayout};
+use std::cmp::Reverse;
+use std::fmt;
+use std::sync::Arc;
+
+/// What a node of the search branches on. Either way, every child places exactly one tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Branch {
+    /// One child for each placement of the candidate tile with this index, see
+    /// [`Candidates::tile_index`].
+    Tile(usize),
+    /// One child for each placement that covers this cell. Only allowed if
+    /// [`Candidates::can_branch_on_cell`].
+    Cell(u8, u8),
+}
+
+/// Everything that could be placed next in a node of the search.
+#[derive(Debug)]
+pub struct Candidates<'c> {
+    pub(crate) board: &'c Board,
+    pub(crate) tiles: &'c [Tile],
+    // Parallel to 'children'.
+    pub(crate) tile_indices: Vec<usize>,
+    pub(crate) children: &'c [Vec<Node>],
+    pub(crate) can_branch_on_cell: bool,
+    pub(crate) must_branch_on_cell: bool,
+}
+
+impl Candidates<'_> {
+    /// The board of the node, where covered cells are blocked.
+    pub fn board(&self) -> &Board {
+        self.board
+    }
+
+    /// Number of tiles that still need to be placed, i.e. candidates for [`Branch::Tile`].
+    pub fn num_tiles(&self) -> usize {
+        self.children.len()
+    }
+
+    /// The candidate's tile, as given to the solver.
+    pub fn tile(&self, candidate: usize) -> &Tile {
+        &self.tiles[self.tile_index(candidate)]
+    }
+
+    /// Index of the candidate's tile in the tiles given to the solver.
+    pub fn tile_index(&self, candidate: usize) -> usize {
+        self.tile_indices[candidate]
+    }
+
+    pub fn num_placements(&self, candidate: usize) -> usize {
+        self.children[candidate].len()
+    }
+
+    /// Where the candidate's tile could be placed next, as in [`crate::Outcome::Solved`].
+    pub fn placements(&self, candidate: usize) -> impl Iterator<Item = Placement> + '_ {
+        self.children[candidate]
+            .iter()
+            .map(|child| Placement::from_operation(self.tiles, child.operation()))
+    }
+
+    /// True if every empty cell has to be covered, either because the board must be filled
+    /// completely, or because the remaining tiles leave no slack. The latter only counts if no
+    /// tile has several copies left, since copies are placed in a fixed order.
+    pub fn can_branch_on_cell(&self) -> bool {
+        self.can_branch_on_cell
+    }
+
+    /// True if there is an unlimited supply of some tile. Then branching on a tile would not
+    /// cover the case that it is not placed at all, so only [`Branch::Cell`] is allowed.
+    pub fn must_branch_on_cell(&self) -> bool {
+        self.must_branch_on_cell
+    }
+
+    /// The candidate with the fewest placements.
+    pub fn fewest_placements(&self) -> usize {
+        (0..self.num_tiles())
+            .min_by_key(|&candidate| self.num_placements(candidate))
+            .expect("no candidates")
+    }
+
+    /// The empty cell that is covered by the fewest placements, and the number of those
+    /// placements. Cells that cannot be covered at all are ignored.
+    pub fn most_constrained_cell(&self) -> Option<((u8, u8), usize)> {
+        let width = usize::from(self.board.width());
+        let mut num_covering = vec![0; self.board.count_cells()];
+        for child in self.children.iter().flatten() {
+            let operation = child.operation();
+            for (x, y) in self.layout(operation).cells() {
+                let (x, y) = (usize::from(x + operation.dx), usize::from(y + operation.dy));
+                num_covering[y * width + x] += 1;
+            }
+        }
+        (0..self.board.height())
+            .flat_map(|y| (0..self.board.width()).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                (
+                    (x, y),
+                    num_covering[usize::from(y) * width + usize::from(x)],
+                )
+            })
+            .filter(|&(_, num_placements)| num_placements > 0)
+            .min_by_key(|&(_, num_placements)| num_placements)
+    }
+
+    fn layout(&self, operation: &Operation) -> &TileLayout {
+        let tile = &self.tiles[usize::from(operation.indexed_tile_layout.tile_index)];
+        &tile.get_layouts()[usize::from(operation.indexed_tile_layout.layout_index)]
+    }
+}
+
+pub trait Heuristic: fmt::Debug + Send + Sync {
+    /// A short identifier, e.g. for command-line options.
+    fn name(&self) -> &'static str;
+
+    /// Called once for each expanded node. There is always at least one candidate tile.
+    fn choose(&self, candidates: &Candidates) -> Branch;
+
+    /// Children with a higher priority are explored first. Ties keep the order in which the
+    /// placements were generated, i.e. by layout, then row, then column.
+    fn priority(&self, _board: &Board, _layout: &TileLayout, _dx: u8, _dy: u8) -> i32 {
+        0
+    }
+}
+
+/// Branches on the tile which has the fewest places it can possibly go. If it must branch on a
+/// cell, it simply takes the first empty one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FewestPlacements;
+
+impl Heuristic for FewestPlacements {
+    fn name(&self) -> &'static str {
+        "fewest-placements"
+    }
+
+    fn choose(&self, candidates: &Candidates) -> Branch {
+        if candidates.must_branch_on_cell() {
+            let (x, y) = candidates.board().first_unblocked().expect("board is full");
+            return Branch::Cell(x, y);
+        }
+        Branch::Tile(candidates.fewest_placements())
+    }
+}
+
+/// Like [`FewestPlacements`], but branches on the empty cell with the fewest placements
+/// covering it whenever that gives fewer children. Exactly filled boards are often pinned down
+/// by a single awkward cell.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MostConstrainedCell;
+
+impl Heuristic for MostConstrainedCell {
+    fn name(&self) -> &'static str {
+        "most-constrained-cell"
+    }
+
+    fn choose(&self, candidates: &Candidates) -> Branch {
+        let best_cell = if candidates.can_branch_on_cell() {
+            candidates.most_constrained_cell()
+        } else {
+            None
+        };
+        if candidates.must_branch_on_cell() {
+            let ((x, y), _) = best_cell.expect("some cell cannot be covered");
+            return Branch::Cell(x, y);
+        }
+        let best_tile = candidates.fewest_placements();
+        match best_cell {
+            Some(((x, y), num_children)) if num_children < candidates.num_placements(best_tile) => {
+                Branch::Cell(x, y)
+            }
+            _ => Branch::Tile(best_tile),
+        }
+    }
+}
+
+/// Branches on the largest tile first, since the large tiles are usually the hardest to fit.
+/// Ties are broken by the number of placements.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LargestTileFirst;
+
+impl Heuristic for LargestTileFirst {
+    fn name(&self) -> &'static str {
+        "largest-tile-first"
+    }
+
+    fn choose(&self, candidates: &Candidates) -> Branch {
+        if candidates.must_branch_on_cell() {
+            return FewestPlacements.choose(candidates);
+        }
+        let largest = (0..candidates.num_tiles())
+            .min_by_key(|&candidate| {
+                (
+                    Reverse(candidates.tile(candidate).get_size()),
+                    candidates.num_placements(candidate),
+                )
+            })
+            .expect("no candidates");
+        Branch::Tile(largest)
+    }
+}
+
+/// Branches like [`FewestPlacements`], but first explores the placements that touch the most
+/// blocked cells or board edges, i.e. that fill up corners and leave few holes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MostBlockedNeighbours;
+
+impl Heuristic for MostBlockedNeighbours {
+    fn name(&self) -> &'static str {
+        "most-blocked-neighbours"
+    }
+
+    fn choose(&self, candidates: &Candidates) -> Branch {
+        FewestPlacements.choose(candidates)
+    }
+
+    fn priority(&self, board: &Board, layout: &TileLayout, dx: u8, dy: u8) -> i32 {
+        let mut num_blocked = 0;
+        for (x, y) in layout.cells() {
+            let neighbours = [(-1, 0), (1, 0), (0, -1), (0, 1)].map(|(ox, oy)| {
+                let (nx, ny) = (i32::from(x) + ox, i32::from(y) + oy);
+                (nx, ny, i32::from(dx) + nx, i32::from(dy) + ny)
+            });
+            for (nx, ny, bx, by) in neighbours {
+                let inside_layout = nx >= 0 && ny >= 0 && layout.is_present_at(nx as u8, ny as u8);
+                if inside_layout {
+                    continue;
+                }
+                let on_board = bx >= 0
+                    && by >= 0
+                    && bx < i32::from(board.width())
+                    && by < i32::from(board.height());
+                if !on_board || board.is_blocked_at(bx as u8, by as u8) {
+                    num_blocked += 1;
+                }
+            }
+        }
+        num_blocked
+    }
+}
+
+/// All built-in heuristics, the default one first.
+pub fn builtin() -> Vec<Arc<dyn Heuristic>> {
+    vec![
+        Arc::new(FewestPlacements),
+        Arc::new(MostConstrainedCell),
+        Arc::new(LargestTileFirst),
+        Arc::new(MostBlockedNeighbours),
+    ]
+}
+
+pub fn find(name: &str) -> Option<Arc<dyn Heuristic>> {
+    builtin()
+        .into_iter()
+        .find(|heuristic| heuristic.name() == name)
+}
+
+/// The performance of one heuristic on a collection of puzzles, see [`compare`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comparison {
+    pub name: &'static str,
+    /// Parallel to the puzzles: The steps until the first solution was found, or until the
+    /// puzzle was proven impossible, or until the step budget of the puzzle ran out.
+    pub steps: Vec<usize>,
+    /// Parallel to the puzzles.
+    pub solved: Vec<bool>,
+    /// The number of puzzles where the step budget ran out.
+    pub num_exhausted: usize,
+}
+
+impl Comparison {
+    pub fn total_steps(&self) -> usize {
+        self.steps.iter().sum()
+    }
+}
+
+/// Solves each puzzle with each heuristic, using the step budget of the puzzle.
+pub fn compare(puzzles: &[Puzzle], heuristics: &[Arc<dyn Heuristic>]) -> Vec<Comparison> {
+    heuristics
+        .iter()
+        .map(|heuristic| {
+            let mut comparison = Comparison {
+                name: heuristic.name(),
+                steps: Vec::with_capacity(puzzles.len()),
+                solved: Vec::with_capacity(puzzles.len()),
+                num_exhausted: 0,
+            };
+            for puzzle in puzzles {
+                let outcome = Solver::new(&puzzle.tiles)
+                    .counts(puzzle.counts.clone())
+                    .orientations(puzzle.orientations.clone())
+                    .max_steps(puzzle.max_steps)
+                    .heuristic(heuristic.clone())
+                    .solve(&puzzle.board);
+                comparison.steps.push(outcome.steps_taken());
+                comparison.solved.push(outcome.placements().is_some());
+                if matches!(outcome, crate::Outcome::BudgetExhausted { .. }) {
+                    comparison.num_exhausted += 1;
+                }
+            }
+            comparison
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog;
+    use crate::tile::{Count, Orientation};
+
+    // All ways to pick three pentominoes for a 3×5 rectangle. Most of them are impossible.
+    fn rectangle_corpus() -> Vec<Puzzle> {
+        let tiles = catalog::find("pentominoes").unwrap().get_tiles();
+        let board: Board = "XXXXX\nXXXXX\nXXXXX".parse().unwrap();
+        let mut corpus = Vec::new();
+        for selection in 0u32..(1 << tiles.len()) {
+            if selection.count_ones() != 3 {
+                continue;
+            }
+            let counts = (0..tiles.len())
+                .map(|i| Count::Limited((selection >> i) as u8 & 1))
+                .collect();
+            corpus.push(Puzzle {
+                catalog_name: Some("pentominoes".to_string()),
+                tiles: tiles.to_vec(),
+                counts,
+                orientations: vec![Orientation::RotationsAndReflections; tiles.len()],
+                max_steps: 100_000,
+                board: board.clone(),
+                solution: None,
+            });
+        }
+        corpus
+    }
+
+    #[test]
+    fn test_builtin_names() {
+        for heuristic in builtin() {
+            assert_eq!(find(heuristic.name()).unwrap().name(), heuristic.name());
+        }
+        assert!(find("random").is_none());
+    }
+
+    #[test]
+    fn test_priority() {
+        let board: Board = "·XXX\nXXXX".parse().unwrap();
+        let domino: TileLayout = "XX".parse().unwrap();
+        // Top right corner: Two edges above, one on the right.
+        assert_eq!(MostBlockedNeighbours.priority(&board, &domino, 2, 0), 3);
+        // Top left corner: Blocked on the left, two edges above.
+        assert_eq!(MostBlockedNeighbours.priority(&board, &domino, 1, 0), 3);
+        // Bottom middle: Only the edge below.
+        assert_eq!(MostBlockedNeighbours.priority(&board, &domino, 1, 1), 2);
+    }
+
+    #[test]
+    fn test_compare() {
+        let corpus = rectangle_corpus();
+        assert_eq!(corpus.len(), 220);
+        let comparisons = compare(&corpus, &builtin());
+        assert_eq!(comparisons[0].name, "fewest-placements");
+        // Every heuristic must come to the same conclusion, but not necessarily the same speed.
+        for comparison in &comparisons {
+            assert_eq!(comparison.num_exhausted, 0);
+            assert_eq!(
+                comparison.solved, comparisons[0].solved,
+                "{}",
+                comparison.name
+            );
+        }
+        assert_eq!(comparisons[0].solved.iter().filter(|&&s| s).count(), 7);
+    }
+}
@@ -2,186 +2,860 @@
 
 #[macro_use]
 extern crate lazy_static;
-#[macro_use]
-extern crate static_assertions;
 
 use wasm_bindgen::prelude::*;
 
+mod ascii;
+mod bitset;
 mod board;
+pub mod catalog;
+mod dlx;
+pub mod heuristic;
+mod parity;
+mod placement_table;
+mod puzzle;
 mod search;
+pub mod solver;
 mod tile;
 
-pub const VERSION: usize = 42;
+// The stable Rust API, see 'solver'.
+pub use ascii::ParseError;
+pub use board::{Board, MAX_CELLS};
+pub use heuristic::Heuristic;
+pub use puzzle::{parse_counts, Puzzle, PuzzleError};
+pub use search::CheckpointError;
+pub use solver::{Backend, CountOutcome, Outcome, Placement, Search, Solver};
+pub use tile::{Count, Orientation, ShapeError, Tile, TileLayout, Transform, MAX_SIZE};
+
+pub const VERSION: usize = 54;
 
 #[wasm_bindgen]
 pub fn check_config(
     version: usize,
-    max_board_w: u8,
-    max_board_h: u8,
+    max_board_cells: u32,
     max_tile_size: u8,
+    catalog_name: &str,
     total_tiles: usize,
 ) -> u32 {
     if version != VERSION {
         return 1;
     }
-    if max_board_w != board::MAX_WIDTH {
+    if max_board_cells != board::MAX_CELLS {
         return 2;
     }
-    if max_board_h != board::MAX_HEIGHT {
-        return 3;
-    }
     if max_tile_size != tile::MAX_SIZE {
         return 4;
     }
-    if total_tiles != tile::ALL_TILES.len() {
-        return 5;
+    match catalog::find(catalog_name) {
+        Some(catalog) if catalog.get_tiles().len() == total_tiles => {}
+        _ => return 5,
     }
 
     // Random number that is unlikely to have been generated by accident:
     134250805
 }
 
-const CELL_TO_TILE_LENGTH: usize = (board::MAX_WIDTH * board::MAX_HEIGHT) as usize;
-
 #[derive(Debug, PartialEq)]
 #[wasm_bindgen]
 pub struct Result {
     pub steps_taken: usize,
     pub has_solution: bool,
     pub has_finished: bool,
-    // Ugh. Wasm doesn't know how to deal with small arrays like [u8; CELL_TO_TILE_LENGTH]:
-    // > the trait `IntoWasmAbi` is not implemented for `[u8; 30]`
-    // > the trait `FromWasmAbi` is not implemented for `[u8; 30]`
-    pub row0: u64,
-    pub row1: u64,
-    pub row2: u64,
-    pub row3: u64,
-    pub row4: u64,
-    pub row5: u64,
+    pub width: u8,
+    pub height: u8,
+    // Row by row, one byte per cell. Wasm only knows how to pass a Vec through a getter.
+    cells: Vec<u8>,
+    // One byte per placed piece, giving the index of its tile, i.e. in the catalog or in the
+    // custom tiles. There may be several pieces of the same tile.
+    pieces: Vec<u8>,
+    // Parallel to 'pieces', see 'encode_transform'.
+    transforms: Vec<u8>,
+    // The same solution as ASCII art, e.g. for bug reports. See 'ascii::render_solution'.
+    drawing: String,
 }
 
-fn extract_row(cell_to_tile: [u8; CELL_TO_TILE_LENGTH], row_index: usize) -> u64 {
-    let mut result = 0;
-    for column in 0..(board::MAX_WIDTH as usize) {
-        result <<= 8;
-        result |= cell_to_tile[row_index * board::MAX_WIDTH as usize + column] as u64;
+#[wasm_bindgen]
+impl Result {
+    #[wasm_bindgen(getter)]
+    pub fn cells(&self) -> Vec<u8> {
+        self.cells.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn pieces(&self) -> Vec<u8> {
+        self.pieces.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn transforms(&self) -> Vec<u8> {
+        self.transforms.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn drawing(&self) -> String {
+        self.drawing.clone()
     }
-    result
 }
 
 impl Result {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         steps_taken: usize,
         has_solution: bool,
         has_finished: bool,
-        cell_to_tile: [u8; CELL_TO_TILE_LENGTH],
+        width: u8,
+        height: u8,
+        cells: Vec<u8>,
+        pieces: Vec<u8>,
+        transforms: Vec<u8>,
+        drawing: &str,
     ) -> Result {
+        assert_eq!(cells.len(), usize::from(width) * usize::from(height));
+        assert_eq!(pieces.len(), transforms.len());
         Result {
             steps_taken,
             has_solution,
             has_finished,
-            row0: extract_row(cell_to_tile, 0),
-            row1: extract_row(cell_to_tile, 1),
-            row2: extract_row(cell_to_tile, 2),
-            row3: extract_row(cell_to_tile, 3),
-            row4: extract_row(cell_to_tile, 4),
-            row5: extract_row(cell_to_tile, 5),
+            width,
+            height,
+            cells,
+            pieces,
+            transforms,
+            drawing: drawing.to_string(),
         }
     }
 }
 
-fn decode_tile_indices(tiles_encoded: u32) -> Vec<usize> {
-    let mut tile_indices = Vec::new();
-    for tile_index in 0..tile::ALL_TILES.len() {
-        if 0 != tiles_encoded & (1 << (tile::ALL_TILES.len() - 1 - tile_index)) {
-            tile_indices.push(tile_index);
-        }
+fn decode_orientation(orientation: u8) -> Option<tile::Orientation> {
+    match orientation {
+        0 => Some(tile::Orientation::Fixed),
+        1 => Some(tile::Orientation::Rotations),
+        2 => Some(tile::Orientation::RotationsAndReflections),
+        _ => None,
     }
-    tile_indices
+}
+
+// One byte per available tile, see 'decode_orientation'. On error, returns the code that is
+// reported by 'check_orientations'.
+fn decode_orientations(
+    orientations: &[u8],
+    total_tiles: usize,
+) -> std::result::Result<Vec<tile::Orientation>, u32> {
+    if orientations.len() != total_tiles {
+        return Err(1);
+    }
+    orientations
+        .iter()
+        .map(|&orientation| decode_orientation(orientation).ok_or(2))
+        .collect()
+}
+
+// Returns 0 if there is a valid orientation for each of the 'total_tiles' tiles, or else a code
+// describing the problem.
+#[wasm_bindgen]
+pub fn check_orientations(orientations: &[u8], total_tiles: usize) -> u32 {
+    match decode_orientations(orientations, total_tiles) {
+        Ok(_) => 0,
+        Err(code) => code,
+    }
+}
+
+fn decode_orientations_or_panic(orientations: &[u8], total_tiles: usize) -> Vec<tile::Orientation> {
+    decode_orientations(orientations, total_tiles)
+        .unwrap_or_else(|code| panic!("Invalid orientations, see check_orientations: {}", code))
+}
+
+fn encode_orientation(orientation: tile::Orientation) -> u8 {
+    match orientation {
+        tile::Orientation::Fixed => 0,
+        tile::Orientation::Rotations => 1,
+        tile::Orientation::RotationsAndReflections => 2,
+    }
+}
+
+// The number of clockwise quarter turns, plus 4 if the tile is mirrored (before rotating).
+fn encode_transform(transform: tile::Transform) -> u8 {
+    transform.rotation + if transform.mirrored { 4 } else { 0 }
+}
+
+// Marks a tile of which there are as many copies as needed, see 'tile::Count::Unlimited'.
+const COUNT_UNLIMITED: u8 = 255;
+
+fn decode_count(count: u8) -> tile::Count {
+    match count {
+        COUNT_UNLIMITED => tile::Count::Unlimited,
+        count => tile::Count::Limited(count),
+    }
+}
+
+// Counts that don't fit are reduced, since the search has to stop at some point anyway.
+fn encode_count(count: tile::Count) -> u8 {
+    match count {
+        tile::Count::Limited(count) => count.min(COUNT_UNLIMITED - 1),
+        tile::Count::Unlimited => COUNT_UNLIMITED,
+    }
+}
+
+// One byte per available tile, giving the number of copies. Tiles with a count of zero are left
+// out, so this returns the indices of the remaining tiles and their counts.
+fn decode_tile_counts(tile_counts: &[u8]) -> (Vec<usize>, Vec<tile::Count>) {
+    tile_counts
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count > 0)
+        .map(|(tile_index, &count)| (tile_index, decode_count(count)))
+        .unzip()
 }
 
 fn paint_cells(
     board: &board::Board,
     steps: &search::Result,
+    tiles: &[tile::Tile],
     tile_lookup: &[usize],
-) -> [u8; CELL_TO_TILE_LENGTH] {
+) -> Vec<u8> {
+    let width = usize::from(board.width());
     // 255 for black (not part of the board) and 254 for white (available but unused).
-    let mut cells = [255; CELL_TO_TILE_LENGTH];
-    for y in 0..board::MAX_HEIGHT {
-        for x in 0..board::MAX_WIDTH {
+    let mut cells = vec![255; board.count_cells()];
+    for y in 0..board.height() {
+        for x in 0..board.width() {
             if board.is_blocked_at(x, y) {
                 continue;
             }
-            let cell_index = x + board::MAX_WIDTH * y;
-            cells[cell_index as usize] = 254;
+            let cell_index = usize::from(x) + width * usize::from(y);
+            cells[cell_index] = 254;
         }
     }
     for operation in steps {
         // Note that the 'tile_index' refers to the index in the '&[Tile]' given to
-        // 'State::new()'. We need to translate that to the index in 'tile::ALL_TILES'.
+        // 'State::new()'. We need to translate that to the index in the catalog.
         let local_tile_index = operation.indexed_tile_layout.tile_index;
         let global_tile_index = tile_lookup[local_tile_index as usize];
-        let tile = &tile::ALL_TILES[global_tile_index];
+        let tile = &tiles[local_tile_index as usize];
         let layout = &tile.get_layouts()[operation.indexed_tile_layout.layout_index as usize];
-        for y in 0..tile::MAX_SIZE {
-            for x in 0..tile::MAX_SIZE {
-                if !layout.is_present_at(x, y) {
-                    continue;
-                }
-                let cell_index = (x + operation.dx) + board::MAX_WIDTH * (y + operation.dy);
-                cells[cell_index as usize] = global_tile_index as u8;
-            }
+        for (x, y) in layout.cells() {
+            let cell_index = usize::from(x + operation.dx) + width * usize::from(y + operation.dy);
+            cells[cell_index] = global_tile_index as u8;
         }
     }
     cells
 }
 
+fn list_pieces(
+    steps: &search::Result,
+    tiles: &[tile::Tile],
+    tile_lookup: &[usize],
+) -> (Vec<u8>, Vec<u8>) {
+    steps
+        .iter()
+        .map(|operation| {
+            let local_tile_index = operation.indexed_tile_layout.tile_index as usize;
+            let transform =
+                tiles[local_tile_index].get_transform(operation.indexed_tile_layout.layout_index);
+            (
+                tile_lookup[local_tile_index] as u8,
+                encode_transform(transform),
+            )
+        })
+        .unzip()
+}
+
+fn decode_tiles(
+    catalog: &[tile::Tile],
+    tile_indices: &[usize],
+    orientations: &[u8],
+) -> Vec<tile::Tile> {
+    let orientations = decode_orientations_or_panic(orientations, catalog.len());
+    // Name the tiles after their symbols in the whole catalog, so that drawings of solutions
+    // are consistent, no matter which tiles were selected.
+    let symbols = ascii::tile_symbols(catalog);
+    tile_indices
+        .iter()
+        .map(|&i| {
+            catalog[i]
+                .with_orientation(orientations[i])
+                .named(&symbols[i].to_string())
+        })
+        .collect()
+}
+
+// 'tile::MAX_SIZE' bytes per tile, see 'TileLayout::from_rows'. On error, returns the code that
+// is reported by 'check_custom_tiles'.
+fn decode_custom_tiles(tile_rows: &[u8]) -> std::result::Result<Vec<tile::Tile>, u32> {
+    let row_count = usize::from(tile::MAX_SIZE);
+    if !tile_rows.len().is_multiple_of(row_count) {
+        return Err(1);
+    }
+    if tile_rows.len() / row_count > catalog::MAX_TILES {
+        return Err(2);
+    }
+    tile_rows
+        .chunks(row_count)
+        .map(|rows| {
+            let shape = tile::TileLayout::from_rows(rows);
+            match shape.check_shape() {
+                Ok(()) => Ok(tile::Tile::from_shape(&shape)),
+                Err(tile::ShapeError::Empty) => Err(3),
+                Err(tile::ShapeError::Disconnected) => Err(4),
+            }
+        })
+        .collect()
+}
+
+// Returns 0 if the custom tiles can be used, or else a code describing the first problem.
+#[wasm_bindgen]
+pub fn check_custom_tiles(tile_rows: &[u8]) -> u32 {
+    match decode_custom_tiles(tile_rows) {
+        Ok(_) => 0,
+        Err(code) => code,
+    }
+}
+
+fn decode_catalog(catalog_name: &str) -> &'static [tile::Tile] {
+    catalog::find(catalog_name)
+        .unwrap_or_else(|| panic!("Unknown catalog {}", catalog_name))
+        .get_tiles()
+}
+
+#[wasm_bindgen]
+pub fn catalog_names() -> Vec<String> {
+    catalog::CATALOGS
+        .iter()
+        .map(|catalog| catalog.get_name().to_string())
+        .collect()
+}
+
+// The names of the tiles in the catalog, e.g. the letters printed on the physical pieces.
+#[wasm_bindgen]
+pub fn catalog_tile_names(catalog_name: &str) -> Vec<String> {
+    decode_catalog(catalog_name)
+        .iter()
+        .map(|tile| tile.get_name().to_string())
+        .collect()
+}
+
+// The shapes of the tiles in the catalog, in the same format as the custom tiles.
 #[wasm_bindgen]
-pub fn compute_result(tiles_encoded: u32, board_encoded: u32, max_steps: usize) -> Result {
-    let tile_indices = decode_tile_indices(tiles_encoded);
-    let tiles = tile_indices
+pub fn catalog_tile_rows(catalog_name: &str) -> Vec<u8> {
+    decode_catalog(catalog_name)
         .iter()
-        .map(|&i| tile::ALL_TILES[i].clone())
-        .collect::<Vec<_>>();
-    let board = board::Board::from_encoded(board_encoded);
-    let mut search_state = search::State::new(board.clone(), &tiles);
-    let (steps_taken, raw_result) = search_state.step_at_most(max_steps);
-    let (has_solution, cell_to_tile) = match raw_result {
-        None => (false, [255; CELL_TO_TILE_LENGTH]),
-        Some(steps) => (true, paint_cells(&board, &steps, &tile_indices)),
+        .flat_map(|tile| tile.get_layouts()[0].to_rows())
+        .collect()
+}
+
+fn decode_custom_tiles_or_panic(tile_rows: &[u8]) -> Vec<tile::Tile> {
+    decode_custom_tiles(tile_rows)
+        .unwrap_or_else(|code| panic!("Invalid custom tiles, see check_custom_tiles: {}", code))
+}
+
+fn find_first_solution(
+    catalog: &[tile::Tile],
+    tile_counts: &[u8],
+    board: board::Board,
+    orientations: &[u8],
+    max_steps: usize,
+) -> Result {
+    assert_eq!(tile_counts.len(), catalog.len());
+    let (tile_indices, counts) = decode_tile_counts(tile_counts);
+    let tiles = decode_tiles(catalog, &tile_indices, orientations);
+    let mut search_state = search::State::with_counts(board.clone(), &tiles, counts);
+    let mut solutions = search_state.solutions(max_steps);
+    let raw_result = solutions.next();
+    make_result(
+        &board,
+        &tiles,
+        &tile_indices,
+        solutions.steps_taken(),
+        raw_result.as_ref(),
+        solutions.has_finished(),
+    )
+}
+
+fn make_result(
+    board: &board::Board,
+    tiles: &[tile::Tile],
+    tile_indices: &[usize],
+    steps_taken: usize,
+    raw_result: Option<&search::Result>,
+    has_finished: bool,
+) -> Result {
+    let has_solution = raw_result.is_some();
+    let steps = raw_result.cloned().unwrap_or_default();
+    let cell_to_tile = if has_solution {
+        paint_cells(board, &steps, tiles, tile_indices)
+    } else {
+        vec![255; board.count_cells()]
     };
+    let (pieces, transforms) = list_pieces(&steps, tiles, tile_indices);
     Result::new(
         steps_taken,
         has_solution,
-        !search_state.can_step(),
+        has_finished,
+        board.width(),
+        board.height(),
         cell_to_tile,
+        pieces,
+        transforms,
+        &ascii::render_solution(board, &steps, tiles),
     )
 }
 
+// 'orientations' has one byte per tile of the catalog, see 'check_orientations'.
+#[wasm_bindgen]
+pub fn compute_result(
+    catalog_name: &str,
+    tile_counts: &[u8],
+    board_width: u8,
+    board_height: u8,
+    board_encoded: &[u32],
+    orientations: &[u8],
+    max_steps: usize,
+) -> Result {
+    let board = board::Board::from_encoded(board_width, board_height, board_encoded);
+    let catalog = decode_catalog(catalog_name);
+    find_first_solution(catalog, tile_counts, board, orientations, max_steps)
+}
+
+// Same as 'compute_result', but with the tiles given by 'tile_rows' (see 'check_custom_tiles')
+// instead of a catalog.
+#[wasm_bindgen]
+pub fn compute_custom_result(
+    tile_rows: &[u8],
+    tile_counts: &[u8],
+    board_width: u8,
+    board_height: u8,
+    board_encoded: &[u32],
+    orientations: &[u8],
+    max_steps: usize,
+) -> Result {
+    let catalog = decode_custom_tiles_or_panic(tile_rows);
+    let board = board::Board::from_encoded(board_width, board_height, board_encoded);
+    find_first_solution(&catalog, tile_counts, board, orientations, max_steps)
+}
+
+// A search for the first solution that can be continued, e.g. a few steps per animation frame,
+// instead of picking 'max_steps' up front like 'compute_result'. Exported as 'Solver', but named
+// differently here so that it doesn't clash with 'solver::Solver'.
+#[derive(Debug)]
+#[wasm_bindgen(js_name = Solver)]
+pub struct SolverHandle {
+    board: board::Board,
+    tile_indices: Vec<usize>,
+    state: search::State<'static>,
+    steps_taken: usize,
+    solution: Option<search::Result>,
+}
+
+#[wasm_bindgen(js_class = Solver)]
+impl SolverHandle {
+    // Same arguments as 'compute_result', except that there is no budget.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        catalog_name: &str,
+        tile_counts: &[u8],
+        board_width: u8,
+        board_height: u8,
+        board_encoded: &[u32],
+        orientations: &[u8],
+    ) -> SolverHandle {
+        let board = board::Board::from_encoded(board_width, board_height, board_encoded);
+        let catalog = decode_catalog(catalog_name);
+        SolverHandle::start(catalog, tile_counts, board, orientations)
+    }
+
+    // Same as 'new', but with the tiles given by 'tile_rows' (see 'check_custom_tiles') instead
+    // of a catalog.
+    pub fn new_custom(
+        tile_rows: &[u8],
+        tile_counts: &[u8],
+        board_width: u8,
+        board_height: u8,
+        board_encoded: &[u32],
+        orientations: &[u8],
+    ) -> SolverHandle {
+        let catalog = decode_custom_tiles_or_panic(tile_rows);
+        let board = board::Board::from_encoded(board_width, board_height, board_encoded);
+        SolverHandle::start(&catalog, tile_counts, board, orientations)
+    }
+
+    // Continues the search for at most 'max_steps' steps. Returns true once there is nothing left
+    // to do, either because a solution was found or because there is none.
+    pub fn step(&mut self, max_steps: usize) -> bool {
+        if !self.is_done() {
+            let (steps_done, result_maybe) = self.state.step_at_most(max_steps);
+            self.steps_taken += steps_done;
+            self.solution = result_maybe;
+        }
+        self.is_done()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn steps_taken(&self) -> usize {
+        self.steps_taken
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn has_solution(&self) -> bool {
+        self.solution.is_some()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn has_finished(&self) -> bool {
+        !self.state.can_step()
+    }
+
+    // The solution so far, in the same format as 'compute_result'.
+    pub fn result(&self) -> Result {
+        make_result(
+            &self.board,
+            self.state.tiles(),
+            &self.tile_indices,
+            self.steps_taken,
+            self.solution.as_ref(),
+            self.has_finished(),
+        )
+    }
+}
+
+impl SolverHandle {
+    fn start(
+        catalog: &[tile::Tile],
+        tile_counts: &[u8],
+        board: board::Board,
+        orientations: &[u8],
+    ) -> SolverHandle {
+        assert_eq!(tile_counts.len(), catalog.len());
+        let (tile_indices, counts) = decode_tile_counts(tile_counts);
+        let tiles = decode_tiles(catalog, &tile_indices, orientations);
+        let state = search::State::with_counts(board.clone(), &tiles, counts);
+        SolverHandle {
+            board,
+            tile_indices,
+            state,
+            steps_taken: 0,
+            solution: None,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.solution.is_some() || self.has_finished()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[wasm_bindgen]
+pub enum Uniqueness {
+    Impossible = 0,
+    Unique = 1,
+    Ambiguous = 2,
+    // The budget ran out before finding a second solution.
+    Unknown = 3,
+}
+
+#[derive(Debug, PartialEq)]
+#[wasm_bindgen]
+pub struct SolutionCount {
+    pub steps_taken: usize,
+    // Only exact if 'has_finished' is true, otherwise it is a lower bound.
+    pub num_solutions: usize,
+    pub has_finished: bool,
+    pub uniqueness: Uniqueness,
+}
+
+impl SolutionCount {
+    fn new(steps_taken: usize, num_solutions: usize, has_finished: bool) -> SolutionCount {
+        let uniqueness = match (num_solutions, has_finished) {
+            (0, true) => Uniqueness::Impossible,
+            (1, true) => Uniqueness::Unique,
+            (2.., _) => Uniqueness::Ambiguous,
+            (_, false) => Uniqueness::Unknown,
+        };
+        SolutionCount {
+            steps_taken,
+            num_solutions,
+            has_finished,
+            uniqueness,
+        }
+    }
+}
+
+impl From<solver::CountOutcome> for SolutionCount {
+    fn from(count: solver::CountOutcome) -> SolutionCount {
+        SolutionCount::new(count.steps_taken, count.num_solutions, count.has_finished)
+    }
+}
+
+fn count_all_solutions(
+    catalog: &[tile::Tile],
+    tile_counts: &[u8],
+    board: board::Board,
+    orientations: &[u8],
+    max_steps: usize,
+) -> SolutionCount {
+    assert_eq!(tile_counts.len(), catalog.len());
+    let (tile_indices, counts) = decode_tile_counts(tile_counts);
+    let tiles = decode_tiles(catalog, &tile_indices, orientations);
+    let mut search_state = search::State::with_counts(board, &tiles, counts);
+    let mut solutions = search_state.solutions(max_steps);
+    let num_solutions = solutions.by_ref().count();
+    SolutionCount::new(
+        solutions.steps_taken(),
+        num_solutions,
+        solutions.has_finished(),
+    )
+}
+
+#[wasm_bindgen]
+pub fn count_solutions(
+    catalog_name: &str,
+    tile_counts: &[u8],
+    board_width: u8,
+    board_height: u8,
+    board_encoded: &[u32],
+    orientations: &[u8],
+    max_steps: usize,
+) -> SolutionCount {
+    let board = board::Board::from_encoded(board_width, board_height, board_encoded);
+    let catalog = decode_catalog(catalog_name);
+    count_all_solutions(catalog, tile_counts, board, orientations, max_steps)
+}
+
+// Same as 'count_solutions', but with the tiles given by 'tile_rows' (see 'check_custom_tiles')
+// instead of a catalog.
+#[wasm_bindgen]
+pub fn count_custom_solutions(
+    tile_rows: &[u8],
+    tile_counts: &[u8],
+    board_width: u8,
+    board_height: u8,
+    board_encoded: &[u32],
+    orientations: &[u8],
+    max_steps: usize,
+) -> SolutionCount {
+    let catalog = decode_custom_tiles_or_panic(tile_rows);
+    let board = board::Board::from_encoded(board_width, board_height, board_encoded);
+    count_all_solutions(&catalog, tile_counts, board, orientations, max_steps)
+}
+
+#[derive(Debug, PartialEq)]
+#[wasm_bindgen]
+pub struct ParsedDrawing {
+    pub width: u8,
+    pub height: u8,
+    // See 'board::Board::from_encoded'.
+    board_encoded: Vec<u32>,
+    // One byte per tile of the catalog, giving the number of pieces in the drawing.
+    tile_counts: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl ParsedDrawing {
+    #[wasm_bindgen(getter)]
+    pub fn board_encoded(&self) -> Vec<u32> {
+        self.board_encoded.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tile_counts(&self) -> Vec<u8> {
+        self.tile_counts.clone()
+    }
+}
+
+// Reads the board and tile selection back from a drawing like 'Result::drawing', e.g. to
+// reproduce a bug report. Returns None if the drawing cannot be parsed.
+#[wasm_bindgen]
+pub fn parse_drawing(catalog_name: &str, drawing: &str) -> Option<ParsedDrawing> {
+    let catalog = decode_catalog(catalog_name);
+    let (board, steps) = ascii::parse_solution(drawing, catalog).ok()?;
+    let mut tile_counts = vec![0u8; catalog.len()];
+    for operation in &steps {
+        let count = &mut tile_counts[usize::from(operation.indexed_tile_layout.tile_index)];
+        *count = count.saturating_add(1).min(COUNT_UNLIMITED - 1);
+    }
+    Some(ParsedDrawing {
+        width: board.width(),
+        height: board.height(),
+        board_encoded: board.to_encoded(),
+        tile_counts,
+    })
+}
+
+#[derive(Debug)]
+#[wasm_bindgen]
+pub struct LoadedPuzzle {
+    // Empty if the puzzle uses custom tiles.
+    catalog_name: String,
+    // The tiles of the catalog or the custom tiles, see 'check_custom_tiles'.
+    tile_rows: Vec<u8>,
+    tile_counts: Vec<u8>,
+    pub width: u8,
+    pub height: u8,
+    board_encoded: Vec<u32>,
+    // One byte per tile, see 'check_orientations'.
+    orientations: Vec<u8>,
+    pub max_steps: usize,
+    // Empty if the puzzle has no known solution, see 'Result::drawing'.
+    solution: String,
+}
+
+#[wasm_bindgen]
+impl LoadedPuzzle {
+    #[wasm_bindgen(getter)]
+    pub fn catalog_name(&self) -> String {
+        self.catalog_name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tile_rows(&self) -> Vec<u8> {
+        self.tile_rows.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn tile_counts(&self) -> Vec<u8> {
+        self.tile_counts.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn board_encoded(&self) -> Vec<u32> {
+        self.board_encoded.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn orientations(&self) -> Vec<u8> {
+        self.orientations.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn solution(&self) -> String {
+        self.solution.clone()
+    }
+}
+
+// Reads a puzzle file, see 'puzzle::Puzzle'. Returns None if the file is invalid.
+#[wasm_bindgen]
+pub fn load_puzzle(text: &str) -> Option<LoadedPuzzle> {
+    let puzzle = text.parse::<puzzle::Puzzle>().ok()?;
+    let solution = puzzle.solution.as_ref().map_or(String::new(), |steps| {
+        ascii::render_solution(&puzzle.board, steps, &puzzle.tiles)
+    });
+    Some(LoadedPuzzle {
+        catalog_name: puzzle.catalog_name.unwrap_or_default(),
+        tile_rows: puzzle
+            .tiles
+            .iter()
+            .flat_map(|tile| tile.get_layouts()[0].to_rows())
+            .collect(),
+        tile_counts: puzzle.counts.into_iter().map(encode_count).collect(),
+        width: puzzle.board.width(),
+        height: puzzle.board.height(),
+        board_encoded: puzzle.board.to_encoded(),
+        orientations: puzzle
+            .orientations
+            .into_iter()
+            .map(encode_orientation)
+            .collect(),
+        max_steps: puzzle.max_steps,
+        solution,
+    })
+}
+
+// Writes a puzzle file for the given catalog, with the same arguments as 'compute_result'.
+#[wasm_bindgen]
+pub fn save_puzzle(
+    catalog_name: &str,
+    tile_counts: &[u8],
+    board_width: u8,
+    board_height: u8,
+    board_encoded: &[u32],
+    orientations: &[u8],
+    max_steps: usize,
+) -> String {
+    let catalog = decode_catalog(catalog_name);
+    assert_eq!(tile_counts.len(), catalog.len());
+    let counts = tile_counts
+        .iter()
+        .map(|&count| decode_count(count))
+        .collect();
+    let puzzle = puzzle::Puzzle {
+        catalog_name: Some(catalog_name.to_string()),
+        tiles: catalog.to_vec(),
+        counts,
+        orientations: decode_orientations_or_panic(orientations, catalog.len()),
+        max_steps,
+        board: board::Board::from_encoded(board_width, board_height, board_encoded),
+        solution: None,
+    };
+    puzzle.to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::cmp::max;
 
+    // The size of the boards in the original game.
+    const WIDTH: u8 = 5;
+    const HEIGHT: u8 = 6;
+    // One byte per tile of the UBONGO catalog.
+    const ALL_ORIENTATIONS: &[u8] = &[2; 12];
+    const UBONGO: &str = "ubongo";
+
+    // Converts the bitmask notation of the old API to one copy of each selected tile. The MSB
+    // corresponds to the first tile.
+    fn one_each(tiles_encoded: u32) -> Vec<u8> {
+        (0..tile::ALL_TILES.len())
+            .map(|tile_index| {
+                let bit = 1 << (tile::ALL_TILES.len() - 1 - tile_index);
+                u8::from(0 != tiles_encoded & bit)
+            })
+            .collect()
+    }
+
     #[test]
-    fn test_decode_tiles() {
-        assert_eq!(decode_tile_indices(0x000), vec![]);
-        assert_eq!(decode_tile_indices(0x001), vec![11]);
-        assert_eq!(decode_tile_indices(0x002), vec![10]);
-        assert_eq!(decode_tile_indices(0x004), vec![9]);
-        assert_eq!(decode_tile_indices(0x008), vec![8]);
-        assert_eq!(decode_tile_indices(0x010), vec![7]);
-        assert_eq!(decode_tile_indices(0x020), vec![6]);
-        assert_eq!(decode_tile_indices(0x040), vec![5]);
-        assert_eq!(decode_tile_indices(0x080), vec![4]);
-        assert_eq!(decode_tile_indices(0x100), vec![3]);
-        assert_eq!(decode_tile_indices(0x200), vec![2]);
-        assert_eq!(decode_tile_indices(0x400), vec![1]);
-        assert_eq!(decode_tile_indices(0x800), vec![0]);
-        assert_eq!(decode_tile_indices(0x123), vec![3, 6, 10, 11]);
+    fn test_check_config() {
+        let max_cells = board::MAX_CELLS;
+        let expected = 134250805;
+        assert_eq!(check_config(VERSION, max_cells, 8, UBONGO, 12), expected);
+        assert_eq!(
+            check_config(VERSION, max_cells, 8, "pentominoes", 12),
+            expected
+        );
+        assert_eq!(check_config(VERSION, max_cells, 8, "hexominoes", 12), 5);
+        assert_eq!(check_config(VERSION, max_cells, 8, "nonexistent", 12), 5);
+        assert_eq!(check_config(VERSION - 1, max_cells, 8, UBONGO, 12), 1);
+    }
+
+    #[test]
+    fn test_catalog_tiles() {
+        assert_eq!(catalog_names()[0], UBONGO);
+        assert_eq!(
+            catalog_tile_names("tetrominoes"),
+            vec!["I", "O", "T", "S", "L"]
+        );
+        let rows = catalog_tile_rows(UBONGO);
+        assert_eq!(rows.len(), 12 * 8);
+        // "A" is the 2×2 square:
+        assert_eq!(rows[0..8], [0x03, 0x03, 0, 0, 0, 0, 0, 0]);
+        // The rows can be used as custom tiles:
+        assert_eq!(check_custom_tiles(&catalog_tile_rows("hexominoes")), 0);
+    }
+
+    #[test]
+    fn test_decode_tile_counts() {
+        assert_eq!(decode_tile_counts(&[0; 12]), (vec![], vec![]));
+        assert_eq!(
+            decode_tile_counts(&one_each(0x123)),
+            (vec![3, 6, 10, 11], vec![tile::Count::Limited(1); 4])
+        );
         assert_eq!(
-            decode_tile_indices(0xFFF),
-            vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
+            decode_tile_counts(&[0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 255, 1]),
+            (
+                vec![1, 10, 11],
+                vec![
+                    tile::Count::Limited(2),
+                    tile::Count::Unlimited,
+                    tile::Count::Limited(1),
+                ]
+            )
         );
     }
 
@@ -195,19 +869,29 @@ mod tests {
             //     ·XXX·
             //     ·····
             //     ····· MSB
-            compute_result(0x062, 0x000779E6, 100),
+            compute_result(UBONGO, &one_each(0x062), WIDTH, HEIGHT, &[0x000779E6], ALL_ORIENTATIONS, 100),
             Result::new(
                 13,
                 true,
                 false,
-                [
+                WIDTH,
+                HEIGHT,
+                vec![
                     255, 0xA, 0x5, 255, 255,
                     0xA, 0xA, 0x5, 0x5, 255,
                     255, 0xA, 0x5, 0x6, 0x6,
                     255, 0xA, 0x6, 0x6, 255,
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
-                ]
+                ],
+                vec![0x5, 0x6, 0xA],
+                vec![1, 4, 4],
+                "·LF··\n\
+                 LLFF·\n\
+                 ·LFGG\n\
+                 ·LGG·\n\
+                 ·····\n\
+                 ·····",
             )
         );
     }
@@ -222,19 +906,29 @@ mod tests {
             //     ·XXX·
             //     ·····
             //     ····· MSB
-            compute_result(0x062, 0x000779E5, 100),
+            compute_result(UBONGO, &one_each(0x062), WIDTH, HEIGHT, &[0x000779E5], ALL_ORIENTATIONS, 100),
             Result::new(
-                12,
+                1,
                 false,
                 true,
-                [
+                WIDTH,
+                HEIGHT,
+                vec![
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
-                ]
+                ],
+                vec![],
+                vec![],
+                "_·_··\n\
+                 ____·\n\
+                 ·____\n\
+                 ·___·\n\
+                 ·····\n\
+                 ·····",
             )
         );
     }
@@ -249,26 +943,369 @@ mod tests {
             //     ·XXX·
             //     ·····
             //     ····· MSB
-            compute_result(0x062, 0x000779E6, 10),
+            compute_result(UBONGO, &one_each(0x062), WIDTH, HEIGHT, &[0x000779E6], ALL_ORIENTATIONS, 10),
             Result::new(
                 10,
                 false,
                 false,
-                [
+                WIDTH,
+                HEIGHT,
+                vec![
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
                     255, 255, 255, 255, 255,
-                ]
+                ],
+                vec![],
+                vec![],
+                "·__··\n\
+                 ____·\n\
+                 ·____\n\
+                 ·___·\n\
+                 ·····\n\
+                 ·····",
+            )
+        );
+    }
+
+    #[test]
+    fn test_solver_handle() {
+        for board_encoded in [0x000779E6, 0x000779E5] {
+            let expected = compute_result(
+                UBONGO,
+                &one_each(0x062),
+                WIDTH,
+                HEIGHT,
+                &[board_encoded],
+                ALL_ORIENTATIONS,
+                100,
+            );
+            let mut handle = SolverHandle::new(
+                UBONGO,
+                &one_each(0x062),
+                WIDTH,
+                HEIGHT,
+                &[board_encoded],
+                ALL_ORIENTATIONS,
+            );
+            assert_eq!(handle.result().steps_taken, 0);
+            assert!(!handle.result().has_finished);
+            loop {
+                let steps_before = handle.steps_taken();
+                let is_done = handle.step(4);
+                // The step that finds the solution counts as well, so this never overshoots.
+                assert!(handle.steps_taken() - steps_before <= 4);
+                if is_done {
+                    break;
+                }
+                assert_eq!(handle.steps_taken() - steps_before, 4);
+                assert!(!handle.has_solution());
+            }
+            assert_eq!(handle.steps_taken(), expected.steps_taken);
+            assert_eq!(handle.result(), expected);
+            // Stepping again has no effect.
+            assert!(handle.step(4));
+            assert_eq!(handle.result(), expected);
+        }
+    }
+
+    #[test]
+    fn test_other_dimensions() {
+        #[rustfmt::skip]
+        assert_eq!(
+            // LSB ···XXXX
+            //     ······X
+            //     ······X
+            //     ······X MSB
+            compute_result(UBONGO, &one_each(0x300), 7, 4, &[0x0810_2078], ALL_ORIENTATIONS, 100),
+            Result::new(
+                2,
+                true,
+                false,
+                7,
+                4,
+                vec![
+                    255, 255, 255, 0x2, 0x2, 0x2, 0x3,
+                    255, 255, 255, 255, 255, 255, 0x3,
+                    255, 255, 255, 255, 255, 255, 0x3,
+                    255, 255, 255, 255, 255, 255, 0x3,
+                ],
+                vec![0x2, 0x3],
+                vec![0, 1],
+                "···CCCD\n\
+                 ······D\n\
+                 ······D\n\
+                 ······D",
             )
         );
     }
 
+    #[test]
+    fn test_orientation_policy() {
+        // Same board as in test_other_dimensions, where one of the straight tiles must be
+        // placed vertically.
+        let fixed = count_solutions(UBONGO, &one_each(0x300), 7, 4, &[0x0810_2078], &[0; 12], 100);
+        assert_eq!(fixed.uniqueness, Uniqueness::Impossible);
+        let rotations = count_solutions(UBONGO, &one_each(0x300), 7, 4, &[0x0810_2078], &[1; 12], 100);
+        assert_eq!(rotations.num_solutions, 2);
+        assert!(rotations.has_finished);
+    }
+
+    #[test]
+    fn test_orientation_no_reflections() {
+        // Same board as in test_simple_positive, whose only solution mirrors two tiles.
+        let result = count_solutions(UBONGO, &one_each(0x062), WIDTH, HEIGHT, &[0x000779E6], &[1; 12], 100);
+        assert_eq!(result.uniqueness, Uniqueness::Impossible);
+    }
+
+    #[test]
+    fn test_count_unique() {
+        // Same board as in test_simple_positive.
+        assert_eq!(
+            count_solutions(UBONGO, &one_each(0x062), WIDTH, HEIGHT, &[0x000779E6], ALL_ORIENTATIONS, 100),
+            SolutionCount {
+                steps_taken: 21,
+                num_solutions: 1,
+                has_finished: true,
+                uniqueness: Uniqueness::Unique,
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_impossible() {
+        // Same board as in test_simple_negative_impossible.
+        assert_eq!(
+            count_solutions(UBONGO, &one_each(0x062), WIDTH, HEIGHT, &[0x000779E5], ALL_ORIENTATIONS, 100),
+            SolutionCount {
+                steps_taken: 1,
+                num_solutions: 0,
+                has_finished: true,
+                uniqueness: Uniqueness::Impossible,
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_ambiguous() {
+        // LSB XXXXX
+        //     ·····
+        //     ·····
+        //     ·····
+        //     ·····
+        //     ····· MSB
+        // The straight 2-tile and 3-tile can be placed in either order.
+        let result = count_solutions(UBONGO, &one_each(0x600), WIDTH, HEIGHT, &[0x0000001F], ALL_ORIENTATIONS, 100);
+        assert_eq!(result.num_solutions, 2);
+        assert!(result.has_finished);
+        assert_eq!(result.uniqueness, Uniqueness::Ambiguous);
+    }
+
+    #[test]
+    fn test_count_timeout() {
+        assert_eq!(
+            count_solutions(UBONGO, &one_each(0x062), WIDTH, HEIGHT, &[0x000779E6], ALL_ORIENTATIONS, 10),
+            SolutionCount {
+                steps_taken: 10,
+                num_solutions: 0,
+                has_finished: false,
+                uniqueness: Uniqueness::Unknown,
+            }
+        );
+    }
+
+    #[test]
+    fn test_count_copies() {
+        // LSB XXXXX
+        //     XXXXX
+        //     ·····
+        //     ·····
+        //     ·····
+        //     ····· MSB
+        // Two straight 4-tiles and one 2-tile (index 1): The 2-tile must stand upright at either
+        // end. Without symmetry breaking, the 4-tiles could also be swapped.
+        let mut tile_counts = [0; 12];
+        tile_counts[1] = 1;
+        tile_counts[3] = 2;
+        let board = [0x000003FF];
+        let result = count_solutions(UBONGO, &tile_counts, WIDTH, HEIGHT, &board, ALL_ORIENTATIONS, 1000);
+        assert_eq!(result.num_solutions, 2);
+        assert!(result.has_finished);
+        let result = compute_result(UBONGO, &tile_counts, WIDTH, HEIGHT, &board, ALL_ORIENTATIONS, 1000);
+        assert!(result.has_solution);
+        let mut pieces = result.pieces();
+        pieces.sort();
+        assert_eq!(pieces, vec![1, 3, 3]);
+    }
+
+    #[test]
+    fn test_count_unlimited() {
+        // Same board as in test_count_copies, but filled with any number of 2×2 squares and
+        // 2-tiles.
+        let mut tile_counts = [0; 12];
+        tile_counts[0] = COUNT_UNLIMITED;
+        tile_counts[1] = COUNT_UNLIMITED;
+        let result = count_solutions(
+            UBONGO, &tile_counts,
+            WIDTH,
+            HEIGHT,
+            &[0x000003FF],
+            ALL_ORIENTATIONS,
+            10_000,
+        );
+        assert!(result.has_finished);
+        // Tilings of a 2×n strip with dominoes and squares: a(n) = a(n-1) + 2 a(n-2).
+        assert_eq!(result.num_solutions, 21);
+    }
+
+    // Straight 5-tile and "+" shape, see 'TileLayout::from_rows'.
+    const CUSTOM_TILES: [u8; 16] = [
+        0x1F, 0, 0, 0, 0, 0, 0, 0, //
+        0x02, 0x07, 0x02, 0, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn test_check_custom_tiles() {
+        assert_eq!(check_custom_tiles(&[]), 0);
+        assert_eq!(check_custom_tiles(&CUSTOM_TILES), 0);
+        assert_eq!(check_custom_tiles(&CUSTOM_TILES[..15]), 1);
+        assert_eq!(check_custom_tiles(&[1; 8 * 255]), 2);
+        assert_eq!(check_custom_tiles(&[0; 8]), 3);
+        assert_eq!(check_custom_tiles(&[0x05, 0, 0, 0, 0, 0, 0, 0]), 4);
+    }
+
+    #[test]
+    fn test_check_orientations() {
+        assert_eq!(check_orientations(&[], 0), 0);
+        assert_eq!(check_orientations(&[0, 1, 2], 3), 0);
+        assert_eq!(check_orientations(&[2, 2], 3), 1);
+        assert_eq!(check_orientations(&[0, 3, 2], 3), 2);
+    }
+
+    #[test]
+    fn test_orientation_per_tile() {
+        // Same board as in test_simple_positive, whose only solution mirrors G and L.
+        let mut orientations = [1; 12];
+        let result = count_solutions(UBONGO, &one_each(0x062), WIDTH, HEIGHT, &[0x000779E6], &orientations, 100);
+        assert_eq!(result.uniqueness, Uniqueness::Impossible);
+        orientations[6] = 2;
+        orientations[10] = 2;
+        let result = count_solutions(UBONGO, &one_each(0x062), WIDTH, HEIGHT, &[0x000779E6], &orientations, 100);
+        assert_eq!(result.uniqueness, Uniqueness::Unique);
+    }
+
+    #[test]
+    fn test_custom_tiles() {
+        // LSB ·X···
+        //     XXX··
+        //     ·X···
+        //     XXXXX
+        //     ·····
+        //     ····· MSB
+        let board = [0x000F88E2];
+        let result = compute_custom_result(&CUSTOM_TILES, &[1, 1], WIDTH, HEIGHT, &board, &[2, 2], 100);
+        assert!(result.has_solution);
+        #[rustfmt::skip]
+        assert_eq!(
+            result.cells(),
+            vec![
+                255, 1, 255, 255, 255,
+                1, 1, 1, 255, 255,
+                255, 1, 255, 255, 255,
+                0, 0, 0, 0, 0,
+                255, 255, 255, 255, 255,
+                255, 255, 255, 255, 255,
+            ]
+        );
+        let count = count_custom_solutions(&CUSTOM_TILES, &[1, 1], WIDTH, HEIGHT, &board, &[2, 2], 100);
+        assert_eq!(count.uniqueness, Uniqueness::Unique);
+        // Without the "+", the board has free cells left over, which is allowed:
+        let count = count_custom_solutions(&CUSTOM_TILES, &[1, 0], WIDTH, HEIGHT, &board, &[2, 2], 100);
+        assert_eq!(count.num_solutions, 1);
+    }
+
+    #[test]
+    fn test_parse_drawing() {
+        // Same as test_simple_positive.
+        let result = compute_result(
+            UBONGO,
+            &one_each(0x062),
+            WIDTH,
+            HEIGHT,
+            &[0x000779E6],
+            ALL_ORIENTATIONS,
+            100,
+        );
+        assert_eq!(
+            parse_drawing(UBONGO, &result.drawing()),
+            Some(ParsedDrawing {
+                width: WIDTH,
+                height: HEIGHT,
+                board_encoded: vec![0x000779E6],
+                tile_counts: one_each(0x062),
+            })
+        );
+        assert_eq!(parse_drawing(UBONGO, "AAA"), None);
+        // Drawings of custom tiles can be read back by their index:
+        let result = compute_custom_result(&CUSTOM_TILES, &[1, 0], 5, 1, &[0x1F], &[2, 2], 100);
+        assert_eq!(result.drawing(), "00000");
+        // Two copies of the same tile may touch:
+        let tile_counts = [0, COUNT_UNLIMITED, 0, 0, 0];
+        let result = compute_result("tetrominoes", &tile_counts, 4, 2, &[0xFF], &[2; 5], 100);
+        assert_eq!(result.drawing(), "OOOO\nOOOO");
+        assert_eq!(
+            parse_drawing("tetrominoes", &result.drawing()),
+            Some(ParsedDrawing {
+                width: 4,
+                height: 2,
+                board_encoded: vec![0xFF],
+                tile_counts: vec![0, 2, 0, 0, 0],
+            })
+        );
+    }
+
+    #[test]
+    fn test_save_and_load_puzzle() {
+        // Same as test_simple_positive.
+        let mut tile_counts = one_each(0x062);
+        tile_counts[0] = COUNT_UNLIMITED;
+        let text = save_puzzle(UBONGO, &tile_counts, WIDTH, HEIGHT, &[0x000779E6], &[1; 12], 500);
+        assert_eq!(
+            text,
+            "mebongo-puzzle 1\n\
+             catalog: ubongo\n\
+             tiles: A*inf F G L\n\
+             orientation: rotations\n\
+             max-steps: 500\n\
+             \n\
+             board:\n\
+             ·XX··\n\
+             XXXX·\n\
+             ·XXXX\n\
+             ·XXX·\n\
+             ·····\n\
+             ·····\n"
+        );
+        let loaded = load_puzzle(&text).unwrap();
+        assert_eq!(loaded.catalog_name(), UBONGO);
+        assert_eq!(loaded.tile_rows(), catalog_tile_rows(UBONGO));
+        assert_eq!(loaded.tile_counts(), tile_counts);
+        assert_eq!((loaded.width, loaded.height), (WIDTH, HEIGHT));
+        assert_eq!(loaded.board_encoded(), vec![0x000779E6]);
+        assert_eq!(loaded.orientations(), vec![1; 12]);
+        assert_eq!(loaded.max_steps, 500);
+        assert_eq!(loaded.solution(), "");
+        assert!(load_puzzle("mebongo-puzzle 1").is_none());
+        // Without any tiles:
+        let text = save_puzzle("tetrominoes", &[0; 5], 4, 1, &[0xF], &[2; 5], 100);
+        assert_eq!(load_puzzle(&text).unwrap().tile_counts(), vec![0; 5]);
+    }
+
     fn assert_no_timeout(tiles_encoded: u32, board_encoded: u32) {
         // Use a stricter upper limit to find the threshold:
-        let result = compute_result(tiles_encoded, board_encoded, 10_000);
+        let result = compute_result(UBONGO, &one_each(tiles_encoded), WIDTH, HEIGHT, &[board_encoded], ALL_ORIENTATIONS, 10_000);
         let did_timeout = !result.has_finished && !result.has_solution;
         let judgement = if did_timeout {
             "===BAD==="
@@ -297,16 +1334,16 @@ mod tests {
     #[test]
     #[ignore = "Test takes too long"]
     fn test_never_timeout_near_full() {
-        let full_board = (1 << (board::MAX_WIDTH * board::MAX_HEIGHT)) - 1;
+        let full_board = (1 << (WIDTH * HEIGHT)) - 1;
         for tiles_encoded in 0u32..((1 << tile::ALL_TILES.len()) - 1) {
             if tiles_encoded.count_ones() < 6 {
                 continue;
             }
             assert_no_timeout(tiles_encoded, full_board);
-            for drop_cell in 0..(board::MAX_WIDTH * board::MAX_HEIGHT) {
+            for drop_cell in 0..(WIDTH * HEIGHT) {
                 let remaining_board = full_board & cell_index_to_anti_mask(drop_cell);
                 assert_no_timeout(tiles_encoded, remaining_board);
-                for drop_cell_b in (drop_cell + 1)..(board::MAX_WIDTH * board::MAX_HEIGHT) {
+                for drop_cell_b in (drop_cell + 1)..(WIDTH * HEIGHT) {
                     let remaining_board = remaining_board & cell_index_to_anti_mask(drop_cell_b);
                     assert_no_timeout(tiles_encoded, remaining_board);
                 }
@@ -314,7 +1351,7 @@ mod tests {
         }
     }
 
-    const BOARD_SIZE: usize = CELL_TO_TILE_LENGTH;
+    const BOARD_SIZE: usize = (WIDTH * HEIGHT) as usize;
 
     struct UnionFind {
         lowest_in_component_or_255: [u8; BOARD_SIZE],
@@ -328,12 +1365,12 @@ mod tests {
         }
 
         fn index(x: u8, y: u8) -> usize {
-            usize::from(x + board::MAX_WIDTH * y)
+            usize::from(x + WIDTH * y)
         }
 
         fn run_on(&mut self, board: u32) {
-            for y in 0..board::MAX_HEIGHT {
-                for x in 0..board::MAX_WIDTH {
+            for y in 0..HEIGHT {
+                for x in 0..WIDTH {
                     let idx = Self::index(x, y);
                     if board & (1 << idx) == 0 {
                         continue;
@@ -410,9 +1447,9 @@ mod tests {
         uf.do_anneal();
         if uf.count_components() > 1 {
             let last_root = uf.last_root();
-            if last_root >= board::MAX_WIDTH {
+            if last_root >= WIDTH {
                 // One above the last root. This might connect it with the rest of the board.
-                Some(last_root - board::MAX_WIDTH)
+                Some(last_root - WIDTH)
             } else {
                 // There is no "above", so use the one to it's left.
                 Some(last_root - 1)
@@ -427,8 +1464,8 @@ mod tests {
     #[test]
     #[ignore = "Test takes too long"]
     fn test_never_timeout_three_tiles() {
-        let full_board = (1 << (board::MAX_WIDTH * board::MAX_HEIGHT)) - 1;
-        let tile_encodings = (0u32..((1 << tile::ALL_TILES.len()) - 1))
+        let full_board = (1 << (WIDTH * HEIGHT)) - 1;
+        let _tile_encodings = (0u32..((1 << tile::ALL_TILES.len()) - 1))
             .filter(|e| e.count_ones() == 3)
             .collect::<Vec<_>>();
         // Old school iteration, to support skipping vast chunks of search space:
@@ -476,8 +1513,8 @@ mod tests {
                 next_board = max(next_board, skip_to_board);
                 //print!(">");
             }
-            let rows = vec![
-                (board >> 0) & 0x1F,
+            let rows = [
+                board & 0x1F,
                 (board >> 5) & 0x1F,
                 (board >> 10) & 0x1F,
                 (board >> 15) & 0x1F,
@@ -527,7 +1564,7 @@ mod tests {
                     let reset_rows_mask = (1 << ((row_index - 1) * 5)) - 1;
                     let lowest_bit = 1 << (rows[row_index] | rows[row_index - 1]).trailing_zeros();
                     let increment = lowest_bit << ((row_index - 1) * 5);
-                    let skip_to_board = (board & !reset_rows_mask) | (reset_rows_mask & ALL_ROWS_0X01_PATTERN) + increment;
+                    let skip_to_board = (board & !reset_rows_mask) | ((reset_rows_mask & ALL_ROWS_0X01_PATTERN) + increment);
                     next_board = max(next_board, skip_to_board);
                     //print!("-");
                 }
@@ -555,7 +1592,7 @@ mod tests {
                 continue;
             }
             println!("CHECK {:08x}", board);
-            //for &tiles_encoded in &tile_encodings {
+            //for &tiles_encoded in &_tile_encodings {
             //    assert_no_timeout(tiles_encoded, board);
             //}
         }
@@ -0,0 +1,359 @@
+// Native command-line solver, mainly for scripting checks over a collection of puzzles.
+
+use mebongo::catalog;
+use mebongo::heuristic::{self, Heuristic};
+use mebongo::{parse_counts, Board, CountOutcome, Orientation, Outcome, Puzzle, Solver};
+use std::fs;
+use std::io::{self, Read};
+use std::process::ExitCode;
+use std::sync::Arc;
+
+const USAGE: &str = "\
+Usage: mebongo [OPTIONS] PUZZLE_FILE
+       mebongo [OPTIONS] --board BOARD_FILE --tiles TILES
+       mebongo --compare-heuristics PUZZLE_FILE...
+
+Solves a polyomino puzzle and prints the solution as ASCII art.
+Use '-' instead of a file name to read from stdin.
+
+Options:
+  --board FILE         Board drawing, with 'X' for each available cell
+  --tiles TILES        Tiles by name, for example \"F G L*2 M*inf\"
+  --catalog NAME       Catalog of the tiles (default: ubongo)
+  --orientation RULE   fixed, rotations, or rotations-and-reflections, for all
+                       tiles (overrides the puzzle file)
+  --max-steps N        Step budget of the search
+  --count              Count all solutions instead of stopping at the first one
+  --heuristic NAME     Branching heuristic of the search (default: fewest-placements)
+  --compare-heuristics Solve all given puzzles with each built-in heuristic and
+                       print the steps taken
+  --help               Show this message
+
+Exit codes:
+  0  Solved (with --count: exactly one solution)
+  1  Impossible
+  2  Invalid usage or input
+  3  The step budget ran out before the answer was known
+  4  Ambiguous (only with --count)
+";
+
+const EXIT_SOLVED: u8 = 0;
+const EXIT_IMPOSSIBLE: u8 = 1;
+const EXIT_INVALID: u8 = 2;
+const EXIT_BUDGET: u8 = 3;
+const EXIT_AMBIGUOUS: u8 = 4;
+
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    help: bool,
+    puzzle_files: Vec<String>,
+    board_file: Option<String>,
+    tiles: Option<String>,
+    catalog: Option<String>,
+    orientation: Option<String>,
+    max_steps: Option<usize>,
+    count: bool,
+    heuristic: Option<String>,
+    compare_heuristics: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value_of = |name: &str| args.next().ok_or(format!("Missing value for {}", name));
+        match arg.as_str() {
+            "--help" | "-h" => options.help = true,
+            "--count" => options.count = true,
+            "--compare-heuristics" => options.compare_heuristics = true,
+            "--heuristic" => options.heuristic = Some(value_of(&arg)?),
+            "--board" => options.board_file = Some(value_of(&arg)?),
+            "--tiles" => options.tiles = Some(value_of(&arg)?),
+            "--catalog" => options.catalog = Some(value_of(&arg)?),
+            "--orientation" => options.orientation = Some(value_of(&arg)?),
+            "--max-steps" => {
+                let value = value_of(&arg)?;
+                let max_steps = value
+                    .parse()
+                    .map_err(|_| format!("Invalid step budget {}", value))?;
+                options.max_steps = Some(max_steps);
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => options.puzzle_files.push(arg),
+        }
+    }
+    if options.puzzle_files.len() > 1 && !options.compare_heuristics {
+        return Err(format!("Unexpected argument {}", options.puzzle_files[1]));
+    }
+    Ok(options)
+}
+
+fn read_input(path: &str) -> Result<String, String> {
+    if path == "-" {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .map_err(|e| format!("Cannot read stdin: {}", e))?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))
+    }
+}
+
+fn load_puzzle(options: &Options) -> Result<Puzzle, String> {
+    let mut puzzle = match (
+        options.puzzle_files.first(),
+        &options.board_file,
+        &options.tiles,
+    ) {
+        (Some(_), None, None) if options.catalog.is_some() => {
+            return Err("--catalog only works with --board and --tiles".to_string());
+        }
+        (Some(path), None, None) => read_input(path)?
+            .parse::<Puzzle>()
+            .map_err(|e| format!("Invalid puzzle file {}: {:?}", path, e))?,
+        (None, Some(path), Some(tiles)) => {
+            let board = read_input(path)?
+                .parse::<Board>()
+                .map_err(|e| format!("Invalid board {}: {:?}", path, e))?;
+            let catalog_name = options.catalog.as_deref().unwrap_or("ubongo");
+            let catalog =
+                catalog::find(catalog_name).ok_or(format!("Unknown catalog {}", catalog_name))?;
+            let counts = parse_counts(tiles, catalog.get_tiles())
+                .map_err(|e| format!("Invalid tiles: {:?}", e))?;
+            Puzzle::new(
+                Some(catalog_name.to_string()),
+                catalog.get_tiles().to_vec(),
+                counts,
+                board,
+            )
+        }
+        _ => return Err("Expected either a puzzle file, or a board and tiles".to_string()),
+    };
+    if let Some(orientation) = &options.orientation {
+        let orientation = orientation
+            .parse::<Orientation>()
+            .map_err(|_| format!("Unknown orientation {}", orientation))?;
+        puzzle.orientations.fill(orientation);
+    }
+    if let Some(max_steps) = options.max_steps {
+        puzzle.max_steps = max_steps;
+    }
+    Ok(puzzle)
+}
+
+// Returns the exit code and everything that should be printed.
+fn solve(puzzle: &Puzzle, count: bool, heuristic: Arc<dyn Heuristic>) -> (u8, String) {
+    let solver = Solver::new(&puzzle.tiles)
+        .counts(puzzle.counts.clone())
+        .orientations(puzzle.orientations.clone())
+        .max_steps(puzzle.max_steps)
+        .heuristic(heuristic);
+    let (first, num_solutions, steps_taken, has_finished) = if count {
+        let CountOutcome {
+            first,
+            num_solutions,
+            steps_taken,
+            has_finished,
+        } = solver.count_solutions(&puzzle.board);
+        (first, num_solutions, steps_taken, has_finished)
+    } else {
+        match solver.solve(&puzzle.board) {
+            Outcome::Solved {
+                placements,
+                steps_taken,
+            } => (Some(placements), 1, steps_taken, false),
+            Outcome::Impossible { steps_taken } => (None, 0, steps_taken, true),
+            Outcome::BudgetExhausted { steps_taken } => (None, 0, steps_taken, false),
+        }
+    };
+    let mut output = String::new();
+    if let Some(placements) = &first {
+        output += &solver.render(&puzzle.board, placements);
+        output += "\n";
+    }
+    let exit_code = match (num_solutions, has_finished || !count) {
+        (0, _) if has_finished => {
+            output += &format!("Impossible, proven after {} steps.\n", steps_taken);
+            EXIT_IMPOSSIBLE
+        }
+        (0, _) => {
+            output += &format!("Gave up after {} steps.\n", steps_taken);
+            EXIT_BUDGET
+        }
+        (1, true) => {
+            let unique = if count { "Unique solution" } else { "Solved" };
+            output += &format!("{}, found after {} steps.\n", unique, steps_taken);
+            EXIT_SOLVED
+        }
+        (1, false) => {
+            output += &format!(
+                "Found 1 solution, but gave up looking for more after {} steps.\n",
+                steps_taken
+            );
+            EXIT_BUDGET
+        }
+        (_, finished) => {
+            let bound = if finished { "" } else { "at least " };
+            output += &format!(
+                "Ambiguous, {}{} solutions found after {} steps.\n",
+                bound, num_solutions, steps_taken
+            );
+            EXIT_AMBIGUOUS
+        }
+    };
+    (exit_code, output)
+}
+
+fn compare_heuristics(paths: &[String]) -> Result<String, String> {
+    let puzzles = paths
+        .iter()
+        .map(|path| {
+            read_input(path)?
+                .parse::<Puzzle>()
+                .map_err(|e| format!("Invalid puzzle file {}: {:?}", path, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut output = format!(
+        "{:<24} {:>12} {:>8}\n",
+        "heuristic", "total steps", "gave up"
+    );
+    for comparison in heuristic::compare(&puzzles, &heuristic::builtin()) {
+        output += &format!(
+            "{:<24} {:>12} {:>8}\n",
+            comparison.name,
+            comparison.total_steps(),
+            comparison.num_exhausted
+        );
+    }
+    Ok(output)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprint!("{}\n\n{}", message, USAGE);
+            return ExitCode::from(EXIT_INVALID);
+        }
+    };
+    if options.help {
+        print!("{}", USAGE);
+        return ExitCode::from(EXIT_SOLVED);
+    }
+    if options.compare_heuristics {
+        return match compare_heuristics(&options.puzzle_files) {
+            Ok(output) => {
+                print!("{}", output);
+                ExitCode::from(EXIT_SOLVED)
+            }
+            Err(message) => {
+                eprintln!("{}", message);
+                ExitCode::from(EXIT_INVALID)
+            }
+        };
+    }
+    let heuristic_name = options.heuristic.as_deref().unwrap_or("fewest-placements");
+    let Some(heuristic) = heuristic::find(heuristic_name) else {
+        eprintln!("Unknown heuristic {}", heuristic_name);
+        return ExitCode::from(EXIT_INVALID);
+    };
+    let puzzle = match load_puzzle(&options) {
+        Ok(puzzle) => puzzle,
+        Err(message) => {
+            eprintln!("{}", message);
+            return ExitCode::from(EXIT_INVALID);
+        }
+    };
+    let (exit_code, output) = solve(&puzzle, options.count, heuristic);
+    print!("{}", output);
+    ExitCode::from(exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mebongo::heuristic::FewestPlacements;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn puzzle(board: &str, tiles: &str) -> Puzzle {
+        let catalog = catalog::find("ubongo").unwrap();
+        let mut puzzle = Puzzle::new(
+            None,
+            catalog.get_tiles().to_vec(),
+            parse_counts(tiles, catalog.get_tiles()).unwrap(),
+            board.parse().unwrap(),
+        );
+        puzzle.max_steps = 1000;
+        puzzle
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            parse(&["--count", "--max-steps", "50", "cards/01.txt"]),
+            Ok(Options {
+                puzzle_files: vec!["cards/01.txt".to_string()],
+                max_steps: Some(50),
+                count: true,
+                ..Options::default()
+            })
+        );
+        assert_eq!(
+            parse(&["--board", "-", "--tiles", "F G"]).map(|o| (o.board_file, o.tiles)),
+            Ok((Some("-".to_string()), Some("F G".to_string())))
+        );
+        assert!(parse(&["--max-steps", "many"]).is_err());
+        assert!(parse(&["--tiles"]).is_err());
+        assert!(parse(&["--frobnicate"]).is_err());
+        assert!(parse(&["a.txt", "b.txt"]).is_err());
+        let compare = parse(&["--compare-heuristics", "a.txt", "b.txt"]).unwrap();
+        assert!(compare.compare_heuristics);
+        assert_eq!(compare.puzzle_files, vec!["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn test_load_requires_input() {
+        assert!(load_puzzle(&Options::default()).is_err());
+        let tiles_only = Options {
+            tiles: Some("A".to_string()),
+            ..Options::default()
+        };
+        assert!(load_puzzle(&tiles_only).is_err());
+        let catalog_with_file = Options {
+            puzzle_files: vec!["puzzle.txt".to_string()],
+            catalog: Some("pentominoes".to_string()),
+            ..Options::default()
+        };
+        let error = load_puzzle(&catalog_with_file).unwrap_err();
+        assert!(error.starts_with("--catalog only works with --board and --tiles"));
+    }
+
+    #[test]
+    fn test_solve() {
+        // Same as 'search::tests::test_sample_easy'.
+        let board = "·XX··\nXXXX·\n·XXXX\n·XXX·";
+        let (exit_code, output) = solve(&puzzle(board, "F G L"), false, Arc::new(FewestPlacements));
+        assert_eq!(exit_code, EXIT_SOLVED);
+        assert_eq!(
+            output,
+            "·LF··\nLLFF·\n·LFGG\n·LGG·\nSolved, found after 13 steps.\n"
+        );
+        let (exit_code, output) = solve(&puzzle(board, "F G L"), true, Arc::new(FewestPlacements));
+        assert_eq!(exit_code, EXIT_SOLVED);
+        assert!(output.ends_with("Unique solution, found after 21 steps.\n"));
+        let (exit_code, output) = solve(&puzzle("XXX", "F"), false, Arc::new(FewestPlacements));
+        assert_eq!(exit_code, EXIT_IMPOSSIBLE);
+        assert!(output.starts_with("Impossible"));
+        let (exit_code, _) = solve(&puzzle("XXXXX", "B C"), true, Arc::new(FewestPlacements));
+        assert_eq!(exit_code, EXIT_AMBIGUOUS);
+        let mut slow = puzzle(board, "F G L");
+        slow.max_steps = 5;
+        assert_eq!(
+            solve(&slow, false, Arc::new(FewestPlacements)).0,
+            EXIT_BUDGET
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use crate::board::Board;
+use crate::tile::{Count, Tile};
+
+// Ways to color the cells in black and white. Wherever a tile is placed, it covers a few more
+// cells of one color than of the other, and all tiles together must make up for the difference
+// on the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Coloring {
+    Checkerboard,
+    Columns,
+    Rows,
+}
+
+impl Coloring {
+    const ALL: [Coloring; 3] = [Coloring::Checkerboard, Coloring::Columns, Coloring::Rows];
+
+    fn is_black(self, x: u8, y: u8) -> bool {
+        match self {
+            Coloring::Checkerboard => (x ^ y) & 1 == 0,
+            Coloring::Columns => x & 1 == 0,
+            Coloring::Rows => y & 1 == 0,
+        }
+    }
+
+    // Black minus white cells.
+    fn imbalance(self, cells: impl Iterator<Item = (u8, u8)>) -> i32 {
+        cells
+            .map(|(x, y)| if self.is_black(x, y) { 1 } else { -1 })
+            .sum()
+    }
+}
+
+#[derive(Debug)]
+pub struct Parity {
+    // For each coloring and tile, the distinct imbalances of all its layouts and offsets.
+    imbalances: Vec<Vec<Vec<i32>>>,
+    sizes: Vec<usize>,
+}
+
+impl Parity {
+    pub fn new(tiles: &[Tile]) -> Parity {
+        let mut imbalances = vec![Vec::with_capacity(tiles.len()); Coloring::ALL.len()];
+        for tile in tiles {
+            let mut tile_imbalances = vec![vec![]; Coloring::ALL.len()];
+            for layout in tile.get_layouts() {
+                let cells: Vec<(u8, u8)> = layout.cells().collect();
+                for (coloring_index, coloring) in Coloring::ALL.iter().enumerate() {
+                    // Moving by one cell swaps the colors, or has no effect at all.
+                    let imbalance = coloring.imbalance(cells.iter().copied());
+                    tile_imbalances[coloring_index].extend([-imbalance, imbalance]);
+                }
+            }
+            for (coloring_index, mut tile_imbalances) in tile_imbalances.into_iter().enumerate() {
+                tile_imbalances.sort_unstable();
+                tile_imbalances.dedup();
+                imbalances[coloring_index].push(tile_imbalances);
+            }
+        }
+        Parity {
+            imbalances,
+            sizes: tiles.iter().map(|tile| tile.get_size() as usize).collect(),
+        }
+    }
+
+    // False if the remaining tiles cannot possibly be placed on 'board' under some coloring.
+    // With an unlimited supply, the board must be filled, but as the number of copies is not
+    // known in advance, this is expensive and only checked if 'thorough'.
+    pub fn is_possible(&self, board: &Board, counts: &[Count], thorough: bool) -> bool {
+        let must_fill_board = counts.contains(&Count::Unlimited);
+        if must_fill_board && !thorough {
+            return true;
+        }
+        let cells: Vec<(u8, u8)> = (0..board.height())
+            .flat_map(|y| (0..board.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| !board.is_blocked_at(x, y))
+            .collect();
+        Coloring::ALL
+            .iter()
+            .enumerate()
+            .all(|(coloring_index, &coloring)| {
+                let imbalances = &self.imbalances[coloring_index];
+                let black = cells
+                    .iter()
+                    .filter(|&&(x, y)| coloring.is_black(x, y))
+                    .count();
+                let white = cells.len() - black;
+                if must_fill_board {
+                    self.can_fill(imbalances, counts, black, white)
+                } else {
+                    self.can_place(imbalances, counts, black, white)
+                }
+            })
+    }
+
+    // All limited tiles must be placed, leaving any cells uncovered.
+    fn can_place(
+        &self,
+        imbalances: &[Vec<i32>],
+        counts: &[Count],
+        black: usize,
+        white: usize,
+    ) -> bool {
+        let mut covered_cells = 0;
+        let mut max_imbalance = 0;
+        for (tile_index, &count) in counts.iter().enumerate() {
+            let Count::Limited(count) = count else {
+                unreachable!()
+            };
+            let largest = imbalances[tile_index]
+                .iter()
+                .map(|d| d.unsigned_abs())
+                .max();
+            covered_cells += usize::from(count) * self.sizes[tile_index];
+            max_imbalance += usize::from(count) * largest.unwrap_or(0) as usize;
+        }
+        let Some(slack) = (black + white).checked_sub(covered_cells) else {
+            return false;
+        };
+        // 'reachable[max_imbalance + d]' iff the tiles placed so far can have the imbalance 'd'.
+        let mut reachable = vec![false; 2 * max_imbalance + 1];
+        let mut next = reachable.clone();
+        reachable[max_imbalance] = true;
+        for (tile_index, &count) in counts.iter().enumerate() {
+            let Count::Limited(count) = count else {
+                unreachable!()
+            };
+            for _ in 0..count {
+                next.fill(false);
+                for (index, _) in reachable.iter().enumerate().filter(|(_, &r)| r) {
+                    for &d in &imbalances[tile_index] {
+                        next[(index as i32 + d) as usize] = true;
+                    }
+                }
+                std::mem::swap(&mut reachable, &mut next);
+            }
+        }
+        // The uncovered cells consist of 'uncovered_black' black cells and the rest white.
+        let min_uncovered_black = slack.saturating_sub(white);
+        let max_uncovered_black = slack.min(black);
+        (min_uncovered_black..=max_uncovered_black).any(|uncovered_black| {
+            let covered_black = (black - uncovered_black) as i32;
+            let covered_white = (white - (slack - uncovered_black)) as i32;
+            let index = max_imbalance as i32 + covered_black - covered_white;
+            0 <= index && (index as usize) < reachable.len() && reachable[index as usize]
+        })
+    }
+
+    // All limited tiles and any number of the unlimited ones must cover every cell.
+    fn can_fill(
+        &self,
+        imbalances: &[Vec<i32>],
+        counts: &[Count],
+        black: usize,
+        white: usize,
+    ) -> bool {
+        let num_cells = black + white;
+        let offset = num_cells as i32;
+        // 'reachable[size][offset + d]' iff the tiles placed so far can cover 'size' cells with
+        // the imbalance 'd'. Anything beyond the board can be ignored.
+        let mut reachable = vec![vec![false; 2 * num_cells + 1]; num_cells + 1];
+        reachable[0][num_cells] = true;
+        let shift = |size: usize, index: usize, tile_index: usize| {
+            let tile_size = self.sizes[tile_index];
+            imbalances[tile_index].iter().filter_map(move |&d| {
+                let next_index = index as i32 + d;
+                let fits = size + tile_size <= num_cells && (0..=2 * offset).contains(&next_index);
+                fits.then_some((size + tile_size, next_index as usize))
+            })
+        };
+        for (tile_index, &count) in counts.iter().enumerate() {
+            let Count::Limited(count) = count else {
+                continue;
+            };
+            for _ in 0..count {
+                let mut next = vec![vec![false; 2 * num_cells + 1]; num_cells + 1];
+                for (size, row) in reachable.iter().enumerate() {
+                    for (index, _) in row.iter().enumerate().filter(|(_, &r)| r) {
+                        for (next_size, next_index) in shift(size, index, tile_index) {
+                            next[next_size][next_index] = true;
+                        }
+                    }
+                }
+                reachable = next;
+            }
+        }
+        for (tile_index, &count) in counts.iter().enumerate() {
+            if count != Count::Unlimited {
+                continue;
+            }
+            // Ascending sizes, so that any number of copies can be added.
+            for size in 0..=num_cells {
+                for index in 0..=2 * num_cells {
+                    if reachable[size][index] {
+                        for (next_size, next_index) in shift(size, index, tile_index) {
+                            reachable[next_size][next_index] = true;
+                        }
+                    }
+                }
+            }
+        }
+        reachable[num_cells][num_cells + black - white]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile;
+
+    #[test]
+    fn test_imbalances() {
+        // The straight 4-piece and the T-shaped 4-piece.
+        let tiles = [tile::ALL_TILES[3].clone(), tile::ALL_TILES[5].clone()];
+        let parity = Parity::new(&tiles);
+        assert_eq!(parity.imbalances[0], vec![vec![0], vec![-2, 2]]);
+        assert_eq!(parity.imbalances[1], vec![vec![-4, 0, 4], vec![-2, 0, 2]]);
+        assert_eq!(parity.imbalances[2], vec![vec![-4, 0, 4], vec![-2, 0, 2]]);
+
+        let fixed = [tile::ALL_TILES[3].with_orientation(tile::Orientation::Fixed)];
+        let parity = Parity::new(&fixed);
+        assert_eq!(parity.imbalances[1], vec![vec![0]]);
+        assert_eq!(parity.imbalances[2], vec![vec![-4, 4]]);
+    }
+
+    #[test]
+    fn test_is_possible() {
+        let tiles = [tile::ALL_TILES[5].clone()];
+        let parity = Parity::new(&tiles);
+        let board: Board = "XXXX\nXXXX\nXXXX\nXXXX".parse().unwrap();
+        assert!(parity.is_possible(&board, &[Count::Limited(4)], false));
+        assert!(parity.is_possible(&board, &[Count::Unlimited], true));
+        // Each T covers 3 cells of one color, so an odd number of them cannot be balanced.
+        let board: Board = "XXXXX\nXXXXX\nXXXXX\nXXXXX".parse().unwrap();
+        assert!(!parity.is_possible(&board, &[Count::Limited(5)], false));
+        assert!(!parity.is_possible(&board, &[Count::Unlimited], true));
+        assert!(parity.is_possible(&board, &[Count::Unlimited], false));
+        // But with some slack, it is possible again.
+        let board: Board = "XXXXX\nXXXXX\nXXXXX\nXXXXX\nX....".parse().unwrap();
+        assert!(parity.is_possible(&board, &[Count::Limited(5)], false));
+        assert!(!parity.is_possible(&board, &[Count::Limited(6)], false));
+    }
+}
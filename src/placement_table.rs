@@ -0,0 +1,103 @@
+use crate::board::Board;
+use crate::tile::Tile;
+
+// One way to put a tile onto the board.
+#[derive(Debug)]
+pub struct Fit {
+    pub layout_index: u8,
+    pub dx: u8,
+    pub dy: u8,
+    // The covered cells, see 'Board::tile_mask'.
+    pub mask: Board,
+}
+
+impl Fit {
+    // The order in which 'PlacementTable' lists the fits of each tile.
+    pub fn placement_key(&self) -> (u8, u8, u8) {
+        (self.layout_index, self.dy, self.dx)
+    }
+}
+
+// Every fit of every tile on the initial board. During the search, cells only ever get blocked,
+// so this is computed once, and each later check is a single comparison of bitsets.
+#[derive(Debug)]
+pub struct PlacementTable {
+    // Indexed by the tile index.
+    fits: Vec<Vec<Fit>>,
+}
+
+impl PlacementTable {
+    pub fn new(initial_board: &Board, tiles: &[Tile]) -> PlacementTable {
+        let fits = tiles
+            .iter()
+            .map(|tile| {
+                let mut fits = Vec::new();
+                for (layout_index, layout) in tile.get_layouts().iter().enumerate() {
+                    // Skip offsets where the layout would obviously stick out of the board:
+                    let (layout_width, layout_height) = layout.bounding_box();
+                    let max_dx = initial_board.width().saturating_sub(layout_width.max(1));
+                    let max_dy = initial_board.height().saturating_sub(layout_height.max(1));
+                    for dy in 0..=max_dy {
+                        for dx in 0..=max_dx {
+                            let Some(mask) = initial_board.tile_mask(layout, dx, dy) else {
+                                continue;
+                            };
+                            if initial_board.contains(&mask) {
+                                fits.push(Fit {
+                                    layout_index: layout_index as u8,
+                                    dx,
+                                    dy,
+                                    mask,
+                                });
+                            }
+                        }
+                    }
+                }
+                fits
+            })
+            .collect();
+        PlacementTable { fits }
+    }
+
+    // Sorted by 'Fit::placement_key'.
+    pub fn fits(&self, tile_index: usize) -> &[Fit] {
+        &self.fits[tile_index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile;
+
+    #[test]
+    fn test_fits() {
+        let board: Board = "XXX\nXX.".parse().unwrap();
+        let tiles = [
+            tile::ALL_TILES[0].clone(), // square
+            tile::ALL_TILES[1].clone(), // domino
+        ];
+        let table = PlacementTable::new(&board, &tiles);
+        let offsets = |tile_index| {
+            table
+                .fits(tile_index)
+                .iter()
+                .map(|fit| fit.placement_key())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(offsets(0), vec![(0, 0, 0)]);
+        assert_eq!(
+            offsets(1),
+            vec![(0, 0, 0), (0, 0, 1), (0, 1, 0), (1, 0, 0), (1, 0, 1)]
+        );
+        for (tile_index, tile) in tiles.iter().enumerate() {
+            for fit in table.fits(tile_index) {
+                let layout = &tile.get_layouts()[usize::from(fit.layout_index)];
+                let expected = board.with_blocked_tile(layout, fit.dx, fit.dy).unwrap();
+                let mut actual = board.clone();
+                actual.block(&fit.mask);
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+}
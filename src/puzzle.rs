@@ -0,0 +1,579 @@
+/* A human-editable text format for puzzles, for example:
+ *
+ *     mebongo-puzzle 1
+ *     # Comments start with '#'.
+ *     catalog: ubongo
+ *     tiles: F G L*2 M*inf
+ *     orientation: rotations-and-reflections
+ *     max-steps: 10000
+ *
+ *     board:
+ *     ·XX··
+ *     XXXX·
+ *
+ *     solution:
+ *     ·LF··
+ *     LLFF·
+ *
+ * Each entry is a 'key: value' line. If the value is empty, the entry is a drawing instead,
+ * which continues until the next empty line (see the 'ascii' module).
+ * Instead of a catalog, the tiles can also be given by one 'shape NAME:' drawing each.
+ * 'orientation', 'max-steps' and 'solution' are optional. An 'orientation NAME:' entry overrides
+ * the orientation of a single tile, e.g. one that is printed on one side only.
+ * 'tiles: -' selects no tiles at all, since an empty value would start a drawing.
+ */
+
+use crate::ascii::{self, ParseError};
+use crate::board::Board;
+use crate::catalog;
+use crate::search;
+use crate::tile::{Count, Orientation, Tile, TileLayout};
+use std::fmt;
+use std::str::FromStr;
+
+pub const FORMAT_VERSION: u32 = 1;
+const HEADER: &str = "mebongo-puzzle";
+pub const DEFAULT_MAX_STEPS: usize = 10_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PuzzleError {
+    MissingHeader,
+    UnsupportedVersion(u32),
+    // Neither an entry nor a comment. The line number starts at 1.
+    Syntax(usize),
+    UnknownKey(String),
+    DuplicateKey(String),
+    MissingKey(&'static str),
+    InvalidValue(String),
+    UnknownCatalog(String),
+    UnknownTile(String),
+    InvalidShape(String),
+    Drawing(String, ParseError),
+    // The solution does not fit the board or the selected tiles.
+    InvalidSolution,
+}
+
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    // None if the tiles are custom shapes.
+    pub catalog_name: Option<String>,
+    pub tiles: Vec<Tile>,
+    // Parallel to 'tiles'.
+    pub counts: Vec<Count>,
+    // Parallel to 'tiles'.
+    pub orientations: Vec<Orientation>,
+    pub max_steps: usize,
+    pub board: Board,
+    // Refers to 'tiles', with all of their layouts.
+    pub(crate) solution: Option<search::Result>,
+}
+
+fn encode_orientation(orientation: Orientation) -> &'static str {
+    match orientation {
+        Orientation::Fixed => "fixed",
+        Orientation::Rotations => "rotations",
+        Orientation::RotationsAndReflections => "rotations-and-reflections",
+    }
+}
+
+const ORIENTATIONS: [Orientation; 3] = [
+    Orientation::Fixed,
+    Orientation::Rotations,
+    Orientation::RotationsAndReflections,
+];
+
+fn decode_orientation(value: &str) -> Option<Orientation> {
+    ORIENTATIONS
+        .into_iter()
+        .find(|&orientation| encode_orientation(orientation) == value)
+}
+
+// The same names as in the file format, e.g. for command-line options.
+impl FromStr for Orientation {
+    type Err = PuzzleError;
+
+    fn from_str(value: &str) -> Result<Orientation, PuzzleError> {
+        decode_orientation(value).ok_or_else(|| PuzzleError::InvalidValue(value.to_string()))
+    }
+}
+
+// Stands for an empty selection, since an empty value would start a drawing.
+const NO_TILES: &str = "-";
+
+// Names are written unquoted, so they must not contain any of the separators.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != NO_TILES
+        && !name.contains(|c: char| c.is_whitespace() || c == '*' || c == ':')
+}
+
+enum Value {
+    Line(String),
+    Drawing(String),
+}
+
+// The entries in the order of the file, with comments and the header removed.
+fn split_entries(text: &str) -> Result<Vec<(String, Value)>, PuzzleError> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with('#'))
+        .skip_while(|(_, line)| line.is_empty())
+        .peekable();
+    let header = lines.next().ok_or(PuzzleError::MissingHeader)?.1;
+    let version = header
+        .strip_prefix(HEADER)
+        .and_then(|version| version.trim().parse::<u32>().ok())
+        .ok_or(PuzzleError::MissingHeader)?;
+    if version != FORMAT_VERSION {
+        return Err(PuzzleError::UnsupportedVersion(version));
+    }
+    let mut entries: Vec<(String, Value)> = Vec::new();
+    while let Some((line_index, line)) = lines.next() {
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(':')
+            .ok_or(PuzzleError::Syntax(line_index + 1))?;
+        let (key, value) = (key.trim().to_string(), value.trim());
+        if entries.iter().any(|(k, _)| k == &key) {
+            return Err(PuzzleError::DuplicateKey(key));
+        }
+        let value = if value.is_empty() {
+            let mut drawing = Vec::new();
+            while let Some((_, line)) = lines.next_if(|(_, line)| !line.is_empty()) {
+                drawing.push(line);
+            }
+            Value::Drawing(drawing.join("\n"))
+        } else {
+            Value::Line(value.to_string())
+        };
+        entries.push((key, value));
+    }
+    Ok(entries)
+}
+
+// For example "F G L*2 M*inf", see the format description above.
+pub fn parse_counts(value: &str, tiles: &[Tile]) -> Result<Vec<Count>, PuzzleError> {
+    let mut counts = vec![Count::Limited(0); tiles.len()];
+    if value.trim() == NO_TILES {
+        return Ok(counts);
+    }
+    for entry in value.split_whitespace() {
+        let (name, count) = entry.split_once('*').unwrap_or((entry, "1"));
+        let tile_index = tiles
+            .iter()
+            .position(|tile| tile.get_name() == name)
+            .ok_or_else(|| PuzzleError::UnknownTile(name.to_string()))?;
+        if counts[tile_index] != Count::Limited(0) {
+            return Err(PuzzleError::InvalidValue(entry.to_string()));
+        }
+        counts[tile_index] = match count {
+            "inf" => Count::Unlimited,
+            count => match count.parse::<u8>() {
+                Ok(count) if count > 0 => Count::Limited(count),
+                _ => return Err(PuzzleError::InvalidValue(entry.to_string())),
+            },
+        };
+    }
+    Ok(counts)
+}
+
+impl Puzzle {
+    // All tiles may be rotated and reflected, with the default step budget, and no solution.
+    pub fn new(
+        catalog_name: Option<String>,
+        tiles: Vec<Tile>,
+        counts: Vec<Count>,
+        board: Board,
+    ) -> Puzzle {
+        assert_eq!(tiles.len(), counts.len());
+        Puzzle {
+            catalog_name,
+            orientations: vec![Orientation::RotationsAndReflections; tiles.len()],
+            tiles,
+            counts,
+            max_steps: DEFAULT_MAX_STEPS,
+            board,
+            solution: None,
+        }
+    }
+
+    // The orientation of most tiles, so that the saver only needs to list the exceptions.
+    fn common_orientation(&self) -> Orientation {
+        // On ties, 'max_by_key' picks the last one, i.e. the default.
+        ORIENTATIONS
+            .into_iter()
+            .max_by_key(|&orientation| {
+                self.orientations
+                    .iter()
+                    .filter(|&&o| o == orientation)
+                    .count()
+            })
+            .unwrap()
+    }
+
+    // Whether 'solution' places exactly the selected tiles, in allowed layouts, onto the board.
+    fn is_valid_solution(&self, steps: &search::Result) -> bool {
+        let mut used = vec![0usize; self.tiles.len()];
+        let mut board = self.board.clone();
+        for operation in steps {
+            let tile_index = usize::from(operation.indexed_tile_layout.tile_index);
+            let layout_index = operation.indexed_tile_layout.layout_index;
+            let tile = &self.tiles[tile_index];
+            if !self.orientations[tile_index].allows(tile.get_transform(layout_index)) {
+                return false;
+            }
+            let layout = &tile.get_layouts()[usize::from(layout_index)];
+            match board.with_blocked_tile(layout, operation.dx, operation.dy) {
+                Some(next_board) => board = next_board,
+                None => return false,
+            }
+            used[tile_index] += 1;
+        }
+        let counts_match = self
+            .counts
+            .iter()
+            .zip(&used)
+            .all(|(&count, &used)| match count {
+                Count::Limited(count) => usize::from(count) == used,
+                Count::Unlimited => true,
+            });
+        let is_filled = board.count_unblocked() == 0;
+        counts_match && (is_filled || !self.counts.contains(&Count::Unlimited))
+    }
+}
+
+impl FromStr for Puzzle {
+    type Err = PuzzleError;
+
+    fn from_str(text: &str) -> Result<Puzzle, PuzzleError> {
+        let mut catalog_name = None;
+        let mut shapes = Vec::new();
+        let mut tiles_value = None;
+        let mut orientation = Orientation::RotationsAndReflections;
+        let mut tile_orientations = Vec::new();
+        let mut max_steps = DEFAULT_MAX_STEPS;
+        let mut board = None;
+        let mut solution_drawing = None;
+        for (key, value) in split_entries(text)? {
+            match (key.as_str(), value) {
+                ("catalog", Value::Line(value)) => catalog_name = Some(value),
+                ("tiles", Value::Line(value)) => tiles_value = Some(value),
+                ("orientation", Value::Line(value)) => {
+                    orientation =
+                        decode_orientation(&value).ok_or(PuzzleError::InvalidValue(key))?;
+                }
+                (_, Value::Line(value)) if key.starts_with("orientation ") => {
+                    let name = key["orientation ".len()..].trim().to_string();
+                    let tile_orientation =
+                        decode_orientation(&value).ok_or(PuzzleError::InvalidValue(key))?;
+                    tile_orientations.push((name, tile_orientation));
+                }
+                ("max-steps", Value::Line(value)) => {
+                    max_steps = value.parse().map_err(|_| PuzzleError::InvalidValue(key))?;
+                }
+                ("board", Value::Drawing(drawing)) => {
+                    let parsed = drawing.parse::<Board>();
+                    board = Some(parsed.map_err(|e| PuzzleError::Drawing(key, e))?);
+                }
+                ("solution", Value::Drawing(drawing)) => solution_drawing = Some(drawing),
+                (_, Value::Drawing(drawing)) if key.starts_with("shape ") => {
+                    let name = key["shape ".len()..].trim();
+                    let layout = drawing
+                        .parse::<TileLayout>()
+                        .map_err(|e| PuzzleError::Drawing(key.clone(), e))?;
+                    if !is_valid_name(name) || layout.check_shape().is_err() {
+                        return Err(PuzzleError::InvalidShape(name.to_string()));
+                    }
+                    shapes.push(Tile::from_shape(&layout).named(name));
+                }
+                _ => return Err(PuzzleError::UnknownKey(key)),
+            }
+        }
+        let tiles = match (&catalog_name, shapes.is_empty()) {
+            (Some(name), true) => catalog::find(name)
+                .ok_or_else(|| PuzzleError::UnknownCatalog(name.clone()))?
+                .get_tiles()
+                .to_vec(),
+            (None, false) => shapes,
+            (None, true) => return Err(PuzzleError::MissingKey("catalog")),
+            (Some(_), false) => return Err(PuzzleError::InvalidValue("catalog".to_string())),
+        };
+        let tiles_value = tiles_value.ok_or(PuzzleError::MissingKey("tiles"))?;
+        let counts = parse_counts(&tiles_value, &tiles)?;
+        let mut orientations = vec![orientation; tiles.len()];
+        for (name, tile_orientation) in tile_orientations {
+            let tile_index = tiles
+                .iter()
+                .position(|tile| tile.get_name() == name)
+                .ok_or(PuzzleError::UnknownTile(name))?;
+            orientations[tile_index] = tile_orientation;
+        }
+        let mut puzzle = Puzzle {
+            catalog_name,
+            tiles,
+            counts,
+            orientations,
+            max_steps,
+            board: board.ok_or(PuzzleError::MissingKey("board"))?,
+            solution: None,
+        };
+        if let Some(drawing) = solution_drawing {
+            let (solution_board, steps) = ascii::parse_solution(&drawing, &puzzle.tiles)
+                .map_err(|e| PuzzleError::Drawing("solution".to_string(), e))?;
+            if solution_board != puzzle.board || !puzzle.is_valid_solution(&steps) {
+                return Err(PuzzleError::InvalidSolution);
+            }
+            puzzle.solution = Some(steps);
+        }
+        Ok(puzzle)
+    }
+}
+
+// The saver, which writes the same format that 'from_str' reads.
+impl fmt::Display for Puzzle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, FORMAT_VERSION)?;
+        match &self.catalog_name {
+            Some(name) => writeln!(f, "catalog: {}", name)?,
+            None => {
+                for tile in &self.tiles {
+                    writeln!(f, "shape {}:\n{}\n", tile.get_name(), tile.get_layouts()[0])?;
+                }
+            }
+        }
+        let tiles = self
+            .tiles
+            .iter()
+            .zip(&self.counts)
+            .filter_map(|(tile, &count)| match count {
+                Count::Limited(0) => None,
+                Count::Limited(1) => Some(tile.get_name().to_string()),
+                Count::Limited(count) => Some(format!("{}*{}", tile.get_name(), count)),
+                Count::Unlimited => Some(format!("{}*inf", tile.get_name())),
+            })
+            .collect::<Vec<_>>();
+        if tiles.is_empty() {
+            writeln!(f, "tiles: {}", NO_TILES)?;
+        } else {
+            writeln!(f, "tiles: {}", tiles.join(" "))?;
+        }
+        let orientation = self.common_orientation();
+        writeln!(f, "orientation: {}", encode_orientation(orientation))?;
+        for (tile, &tile_orientation) in self.tiles.iter().zip(&self.orientations) {
+            if tile_orientation != orientation {
+                let name = tile.get_name();
+                writeln!(
+                    f,
+                    "orientation {}: {}",
+                    name,
+                    encode_orientation(tile_orientation)
+                )?;
+            }
+        }
+        writeln!(f, "max-steps: {}", self.max_steps)?;
+        write!(f, "\nboard:\n{}\n", self.board)?;
+        if let Some(solution) = &self.solution {
+            let drawing = ascii::render_solution(&self.board, solution, &self.tiles);
+            write!(f, "\nsolution:\n{}\n", drawing)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "
+        mebongo-puzzle 1
+        # Same as 'search::tests::test_sample_easy'.
+        catalog: ubongo
+        tiles: F G L
+        max-steps: 1000
+
+        board:
+        ·XX··
+        XXXX·
+        ·XXXX
+        ·XXX·
+
+        solution:
+        ·LF··
+        LLFF·
+        ·LFGG
+        ·LGG·
+    ";
+
+    #[test]
+    fn test_load() {
+        let puzzle = SAMPLE.parse::<Puzzle>().unwrap();
+        assert_eq!(puzzle.catalog_name.as_deref(), Some("ubongo"));
+        assert_eq!(puzzle.tiles.len(), 12);
+        assert_eq!(puzzle.counts[5], Count::Limited(1));
+        assert_eq!(puzzle.counts[7], Count::Limited(0));
+        assert_eq!(
+            puzzle.orientations,
+            vec![Orientation::RotationsAndReflections; 12]
+        );
+        assert_eq!(puzzle.max_steps, 1000);
+        assert_eq!(puzzle.board.count_unblocked(), 13);
+        assert_eq!(puzzle.solution.map(|s| s.len()), Some(3));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let puzzle = SAMPLE.parse::<Puzzle>().unwrap();
+        let saved = puzzle.to_string();
+        assert!(saved.starts_with("mebongo-puzzle 1\ncatalog: ubongo\ntiles: F G L\n"));
+        let reloaded = saved.parse::<Puzzle>().unwrap();
+        assert_eq!(reloaded.to_string(), saved);
+        assert_eq!(reloaded.counts, puzzle.counts);
+        assert_eq!(reloaded.board, puzzle.board);
+    }
+
+    #[test]
+    fn test_custom_shapes() {
+        let text = "
+            mebongo-puzzle 1
+            shape bar:
+            XXX
+
+            shape dot:
+            X
+
+            tiles: bar dot*inf
+            orientation: fixed
+
+            board:
+            XXX
+            XXX
+        ";
+        let puzzle = text.parse::<Puzzle>().unwrap();
+        assert_eq!(puzzle.catalog_name, None);
+        assert_eq!(puzzle.tiles[0].get_name(), "bar");
+        assert_eq!(puzzle.tiles[0].get_layouts().len(), 2);
+        assert_eq!(puzzle.counts, vec![Count::Limited(1), Count::Unlimited]);
+        assert_eq!(puzzle.orientations, vec![Orientation::Fixed; 2]);
+        assert_eq!(puzzle.max_steps, DEFAULT_MAX_STEPS);
+        let reloaded = puzzle.to_string().parse::<Puzzle>().unwrap();
+        assert_eq!(reloaded.to_string(), puzzle.to_string());
+    }
+
+    #[test]
+    fn test_no_tiles() {
+        let text = "
+            mebongo-puzzle 1
+            catalog: ubongo
+            tiles: -
+
+            board:
+            XXXX
+        ";
+        let puzzle = text.parse::<Puzzle>().unwrap();
+        assert_eq!(puzzle.counts, vec![Count::Limited(0); 12]);
+        let saved = puzzle.to_string();
+        assert!(saved.contains("\ntiles: -\n"));
+        assert_eq!(saved.parse::<Puzzle>().unwrap().counts, puzzle.counts);
+    }
+
+    #[test]
+    fn test_touching_copies() {
+        let text = "
+            mebongo-puzzle 1
+            catalog: tetrominoes
+            tiles: O*2
+
+            board:
+            XXXX
+            XXXX
+
+            solution:
+            OOOO
+            OOOO
+        ";
+        let puzzle = text.parse::<Puzzle>().unwrap();
+        assert_eq!(puzzle.solution.as_ref().map(|s| s.len()), Some(2));
+        let reloaded = puzzle.to_string().parse::<Puzzle>().unwrap();
+        assert_eq!(reloaded.to_string(), puzzle.to_string());
+    }
+
+    #[test]
+    fn test_tile_orientations() {
+        let text = SAMPLE.replace(
+            "tiles: F G L",
+            "tiles: F G L\norientation: rotations-and-reflections\norientation F: rotations",
+        );
+        let puzzle = text.parse::<Puzzle>().unwrap();
+        let mut expected = vec![Orientation::RotationsAndReflections; 12];
+        expected[5] = Orientation::Rotations;
+        assert_eq!(puzzle.orientations, expected);
+        let saved = puzzle.to_string();
+        assert!(saved.contains(
+            "orientation: rotations-and-reflections\norientation F: rotations\nmax-steps"
+        ));
+        assert_eq!(saved.parse::<Puzzle>().unwrap().orientations, expected);
+        // The sample solution mirrors G and L:
+        let text = SAMPLE.replace("tiles: F G L", "tiles: F G L\norientation L: rotations");
+        assert_eq!(
+            text.parse::<Puzzle>().err(),
+            Some(PuzzleError::InvalidSolution)
+        );
+        let text = SAMPLE.replace("tiles: F G L", "tiles: F G L\norientation I: fixed");
+        assert_eq!(
+            text.parse::<Puzzle>().err(),
+            Some(PuzzleError::UnknownTile("I".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let parse = |text: &str| text.parse::<Puzzle>().err();
+        assert_eq!(parse(""), Some(PuzzleError::MissingHeader));
+        assert_eq!(
+            parse("mebongo-puzzle 2"),
+            Some(PuzzleError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            parse("mebongo-puzzle 1\ncatalog ubongo"),
+            Some(PuzzleError::Syntax(2))
+        );
+        assert_eq!(
+            parse("mebongo-puzzle 1\ncatalog: ubongo\ncatalog: ubongo"),
+            Some(PuzzleError::DuplicateKey("catalog".to_string()))
+        );
+        assert_eq!(
+            parse("mebongo-puzzle 1\ncolor: red"),
+            Some(PuzzleError::UnknownKey("color".to_string()))
+        );
+        assert_eq!(
+            parse("mebongo-puzzle 1\ncatalog: ubongo\ntiles: A\n"),
+            Some(PuzzleError::MissingKey("board"))
+        );
+        assert_eq!(
+            parse("mebongo-puzzle 1\ncatalog: ubongo\ntiles: I\nboard:\nX"),
+            Some(PuzzleError::UnknownTile("I".to_string()))
+        );
+        assert_eq!(
+            parse("mebongo-puzzle 1\ncatalog: ubongo\ntiles: A*0\nboard:\nX"),
+            Some(PuzzleError::InvalidValue("A*0".to_string()))
+        );
+        assert_eq!(
+            parse("mebongo-puzzle 1\ncatalog: ubongo\ntiles: A\nboard:\nX?"),
+            Some(PuzzleError::Drawing(
+                "board".to_string(),
+                ParseError::UnexpectedCharacter('?')
+            ))
+        );
+        assert_eq!(
+            parse("mebongo-puzzle 1\nshape x:\nX·X\n\ntiles: x\nboard:\nX"),
+            Some(PuzzleError::InvalidShape("x".to_string()))
+        );
+        // The solution leaves out "G":
+        let wrong_solution = SAMPLE.replace("·LFGG", "·LF__").replace("·LGG·", "·L__·");
+        assert_eq!(parse(&wrong_solution), Some(PuzzleError::InvalidSolution));
+    }
+}
@@ -1,13 +1,17 @@
-use crate::board::{self, Board};
-use crate::tile::{self, Tile};
+use crate::board::{Board, MAX_CELLS};
+use crate::heuristic::{Branch, Candidates, FewestPlacements, Heuristic};
+use crate::parity::Parity;
+use crate::placement_table::{Fit, PlacementTable};
+use crate::tile::{Count, Tile};
+use std::collections::HashSet;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct IndexedTileLayout {
     pub tile_index: u8,
     pub layout_index: u8,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Operation {
     pub indexed_tile_layout: IndexedTileLayout,
     pub dx: u8,
@@ -25,171 +29,639 @@ impl Operation {
             dy,
         }
     }
+
+    // The order of 'Fit::placement_key'.
+    fn placement_key(&self) -> (u8, u8, u8) {
+        (self.indexed_tile_layout.layout_index, self.dy, self.dx)
+    }
 }
 
 #[derive(Debug)]
-struct Node {
+pub(crate) struct Node {
     board: Board,
-    operation_and_parent_index: Option<(Operation, usize)>,
+    operation: Option<Operation>,
 }
 
 impl Node {
+    // Only the root has no operation, and it is never a child.
+    pub(crate) fn operation(&self) -> &Operation {
+        self.operation.as_ref().unwrap()
+    }
+
     fn new_root(board: Board) -> Node {
         Node {
             board,
-            operation_and_parent_index: None,
-        }
-    }
-
-    fn find_all_fits(&self, own_index: usize, tile: &Tile, tile_index: u8) -> Vec<Node> {
-        let mut result = Vec::new();
-        for (layout_index_us, layout) in tile.get_layouts().iter().enumerate() {
-            let layout_index = layout_index_us as u8;
-            for dy in 0..board::MAX_HEIGHT {
-                for dx in 0..board::MAX_WIDTH {
-                    if let Some(child_board) = self.board.with_blocked_tile(layout, dx, dy) {
-                        let operation = Operation::from(tile_index, layout_index, dx, dy);
-                        let operation_and_parent_index = Some((operation, own_index));
-                        result.push(Node {
-                            board: child_board,
-                            operation_and_parent_index,
-                        });
-                    }
-                }
+            operation: None,
+        }
+    }
+
+    // If 'after' is given, only placements that come strictly after it are considered.
+    fn find_all_fits<'t>(&self, fits: &'t [Fit], after: Option<&Operation>) -> Vec<&'t Fit> {
+        let first = match after {
+            Some(after) => fits.partition_point(|fit| fit.placement_key() <= after.placement_key()),
+            None => 0,
+        };
+        fits[first..]
+            .iter()
+            .filter(|fit| self.board.contains(&fit.mask))
+            .collect()
+    }
+
+    fn child(&self, tile_index: u8, fit: &Fit) -> Node {
+        let mut board = self.board.clone();
+        board.block(&fit.mask);
+        let operation = Operation::from(tile_index, fit.layout_index, fit.dx, fit.dy);
+        Node {
+            board,
+            operation: Some(operation),
+        }
+    }
+}
+
+// Everything that still needs to be placed below a node.
+#[derive(Clone, Debug)]
+struct Remaining {
+    counts: Vec<Count>,
+    // The most recently placed copy of each tile. Identical copies must be placed in the order
+    // of 'Operation::placement_key', otherwise we would explore all their permutations.
+    last_operations: Vec<Option<Operation>>,
+}
+
+impl Remaining {
+    fn after(&self, operation: Option<&Operation>) -> Remaining {
+        let mut counts = self.counts.clone();
+        let mut last_operations = self.last_operations.clone();
+        if let Some(operation) = operation {
+            let tile_index = usize::from(operation.indexed_tile_layout.tile_index);
+            if let Count::Limited(count) = &mut counts[tile_index] {
+                debug_assert!(*count > 0);
+                *count -= 1;
             }
+            last_operations[tile_index] = Some(operation.clone());
+        }
+        Remaining {
+            counts,
+            last_operations,
         }
-        result
     }
 }
 
+// Two nodes with the same key have the same solutions below them.
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct TranspositionKey {
+    board: Board,
+    counts: Vec<Count>,
+    // Only for the tiles with copies left, see 'Remaining::last_operations'.
+    last_operations: Vec<Option<Operation>>,
+}
+
+// A node on the path from the root to the current node, whose children are being explored.
+#[derive(Debug)]
+struct Frame {
+    // None for the frame below the root.
+    operation: Option<Operation>,
+    // Of the children, since the operation has already been applied.
+    remaining: Remaining,
+    // The children that have not been explored yet, the next one last.
+    pending: Vec<Node>,
+    has_solution: bool,
+    key: Option<TranspositionKey>,
+}
+
+// The default size limit of 'State::failed'.
+pub const DEFAULT_MAX_FAILED: usize = 1 << 14;
+
+const CHECKPOINT_HEADER: &str = "mebongo-search";
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckpointError {
+    MissingHeader,
+    UnsupportedVersion(u32),
+    Syntax,
+    // The checkpoint refers to tiles or placements that do not match the given tiles.
+    Mismatch,
+}
+
 #[derive(Debug)]
 pub struct State<'a> {
-    /* Note that deduplication is non-trivial, since achieving the same silhouette does not
-     * necessarily mean that the subtree will look exactly the same, because a different subset
-     * of tiles might be remaining. Therefore, we don't even try.
+    /* Note that achieving the same silhouette does not necessarily mean that the subtree will
+     * look exactly the same, because a different subset of tiles might be remaining. Therefore,
+     * 'failed' remembers the combination of board and remaining tiles whose subtree has been
+     * explored completely without finding a solution. Reaching it again through a different
+     * order of the same placements skips the entire subtree.
      *
      * Invariants:
      * - `tiles.len() > 0`
-     * - `closed` does not contain any solutions
-     * - Depth-First-Search, to reduce memory strain.
+     * - `tiles.len() == counts.len()`
+     * - The last frame has pending children, unless there are no frames left.
+     * - Depth-First-Search, so memory is proportional to the depth, not to the nodes visited.
      */
-    closed: Vec<Node>,
-    open: Vec<Node>,
-    tiles: &'a [Tile],
+    frames: Vec<Frame>,
+    initial_board: Board,
+    // Owned, so that a search can be kept around between calls, see 'crate::SolverHandle'.
+    tiles: Vec<Tile>,
+    counts: Vec<Count>,
+    heuristic: &'a dyn Heuristic,
+    failed: HashSet<TranspositionKey>,
+    max_failed: usize,
+    // Region-size pruning relies on each tile lying within a single region.
+    all_tiles_connected: bool,
+    parity: Parity,
+    placement_table: PlacementTable,
 }
 
 pub type Result = Vec<Operation>;
 
 impl<'a> State<'a> {
-    pub fn new(initial_board: Board, tiles: &'a [Tile]) -> Self {
+    #[cfg(test)]
+    pub fn new(initial_board: Board, tiles: &[Tile]) -> Self {
+        Self::with_counts(initial_board, tiles, vec![Count::Limited(1); tiles.len()])
+    }
+
+    pub fn with_counts(initial_board: Board, tiles: &[Tile], counts: Vec<Count>) -> Self {
+        assert_eq!(tiles.len(), counts.len());
+        let placement_table = PlacementTable::new(&initial_board, tiles);
+        let root_frame = Frame {
+            operation: None,
+            remaining: Remaining {
+                counts: counts.clone(),
+                last_operations: vec![None; tiles.len()],
+            },
+            pending: vec![Node::new_root(initial_board.clone())],
+            has_solution: false,
+            key: None,
+        };
         Self {
-            closed: vec![],
-            open: vec![Node::new_root(initial_board)],
-            tiles,
+            frames: vec![root_frame],
+            initial_board,
+            tiles: tiles.to_vec(),
+            counts,
+            heuristic: &FewestPlacements,
+            failed: HashSet::new(),
+            max_failed: DEFAULT_MAX_FAILED,
+            all_tiles_connected: tiles
+                .iter()
+                .all(|tile| tile.get_layouts()[0].check_shape().is_ok()),
+            parity: Parity::new(tiles),
+            placement_table,
         }
     }
 
-    pub fn can_step(&self) -> bool {
-        !self.open.is_empty()
+    // Zero disables the transposition table.
+    pub fn with_max_failed(mut self, max_failed: usize) -> Self {
+        self.max_failed = max_failed;
+        self
     }
 
-    fn compute_remaining_tiles(&self, node: &Node) -> Vec<u8> {
-        let mut bitvec = vec![true; self.tiles.len()];
-        let mut walk_node = node;
-        while let Some((operation, parent_index)) = &walk_node.operation_and_parent_index {
-            let tile_index = usize::from(operation.indexed_tile_layout.tile_index);
-            debug_assert!(bitvec[tile_index]);
-            bitvec[tile_index] = false;
-            walk_node = &self.closed[*parent_index];
-        }
-        bitvec
-            .iter()
-            .enumerate()
-            .filter_map(|(tile_index, &tile_available)| {
-                if tile_available {
-                    Some(tile_index as u8)
-                } else {
-                    None
-                }
-            })
-            .collect()
+    pub fn with_heuristic(mut self, heuristic: &'a dyn Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    // The tiles given to 'with_counts', which 'Result' refers to.
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    pub fn can_step(&self) -> bool {
+        !self.frames.is_empty()
     }
 
     fn as_result(&self, node: &Node) -> Result {
         let mut result = Vec::with_capacity(self.tiles.len());
-        let mut walk_node = node;
-        while let Some((operation, parent_index)) = &walk_node.operation_and_parent_index {
-            result.push(operation.clone());
-            walk_node = &self.closed[*parent_index];
+        result.extend(node.operation.clone());
+        result.extend(
+            self.frames
+                .iter()
+                .rev()
+                .filter_map(|frame| frame.operation.clone()),
+        );
+        if !self.counts.contains(&Count::Unlimited) {
+            let total_count = self.counts.iter().map(|count| match count {
+                Count::Limited(count) => usize::from(*count),
+                Count::Unlimited => unreachable!(),
+            });
+            assert!(result.len() == total_count.sum());
         }
-        assert!(result.len() == self.tiles.len());
         result
     }
 
     pub fn step_single(&mut self) -> Option<Result> {
-        let node = self.open.pop().expect("Forgot can_step()???");
-        // TODO: Perhaps it is possible to drop all entries in `closed` beyond `node.parent_index`?
-        let remaining_tile_indices = self.compute_remaining_tiles(&node);
-        if remaining_tile_indices.len() == 0 {
-            return Some(self.as_result(&node));
+        let frame = self.frames.last_mut().expect("Forgot can_step()???");
+        let node = frame.pending.pop().unwrap();
+        let remaining = frame.remaining.after(node.operation.as_ref());
+        let key = self.transposition_key(&node.board, &remaining);
+        if key.as_ref().is_some_and(|key| self.failed.contains(key)) {
+            // Already explored through a different order of the same placements.
+            self.pop_finished_frames();
+            return None;
+        }
+        let limited_tiles_done = remaining
+            .counts
+            .iter()
+            .all(|&count| count == Count::Limited(0) || count == Count::Unlimited);
+        let must_fill_board = remaining.counts.contains(&Count::Unlimited);
+        if limited_tiles_done && (!must_fill_board || node.board.count_unblocked() == 0) {
+            let result = self.as_result(&node);
+            self.frames.last_mut().unwrap().has_solution = true;
+            self.pop_finished_frames();
+            return Some(result);
+        }
+
+        match self.find_children(&node, &remaining) {
+            Some(children) if !children.is_empty() => {
+                self.frames.push(Frame {
+                    operation: node.operation,
+                    remaining,
+                    pending: children,
+                    has_solution: false,
+                    key,
+                });
+            }
+            _ => {
+                if let Some(key) = key {
+                    self.record_failure(key);
+                }
+                self.pop_finished_frames();
+            }
         }
-        let next_parent_index = self.closed.len();
+
+        // If any of the children are a solution, then *all* children are solutions,
+        // so it's okay to delay by one step.
+        None
+    }
+
+    // The children of a node that is not a solution, or None if it is obviously a dead end.
+    fn find_children(&self, node: &Node, remaining: &Remaining) -> Option<Vec<Node>> {
+        // With an unlimited supply, there is no natural end, so the board must be filled instead.
+        let must_fill_board = remaining.counts.contains(&Count::Unlimited);
 
         let mut dead_cells = node.board.clone();
         let mut remaining_tile_cells = 0;
+        let mut case_distinctions: Vec<Vec<Node>> = Vec::with_capacity(self.tiles.len());
+        let mut tile_indices = Vec::with_capacity(self.tiles.len());
+        for (tile_index, &count) in remaining.counts.iter().enumerate() {
+            let tile = &self.tiles[tile_index];
+            match count {
+                Count::Limited(0) => continue,
+                Count::Limited(count) => remaining_tile_cells += u32::from(count) * tile.get_size(),
+                Count::Unlimited => {}
+            }
+            // Placing the tiles cell by cell (see below) never results in permutations.
+            let after = if must_fill_board {
+                None
+            } else {
+                remaining.last_operations[tile_index].as_ref()
+            };
+            let fits = node.find_all_fits(self.placement_table.fits(tile_index), after);
+            if fits.is_empty() && count != Count::Unlimited {
+                // There is a tile which cannot be placed, therefore we don't need to consider this subtree at all.
+                return None;
+            }
+            for fit in &fits {
+                dead_cells.block(&fit.mask);
+            }
+            let case_distinction = fits
+                .into_iter()
+                .map(|fit| node.child(tile_index as u8, fit))
+                .collect();
+            case_distinctions.push(case_distinction);
+            tile_indices.push(tile_index);
+        }
 
-        // Now search for the tile which has the fewest places it can possibly go:
-        let best_case_distinction = remaining_tile_indices
-            .iter()
-            .inspect(|&&tile_index| {
-                remaining_tile_cells += &self.tiles[tile_index as usize].get_size();
-            })
-            .map(|&tile_index| {
-                node.find_all_fits(
-                    next_parent_index,
-                    &self.tiles[tile_index as usize],
-                    tile_index,
-                )
-            })
-            .inspect(|case_distinction| {
-                if dead_cells.count_unblocked() == 0 {
-                    return; // no dead cells anymore
-                }
-                for node in case_distinction {
-                    let operation = &node.operation_and_parent_index.as_ref().unwrap().0;
-                    let tile_layout = &self.tiles
-                        [operation.indexed_tile_layout.tile_index as usize]
-                        .get_layouts()
-                        [operation.indexed_tile_layout.layout_index as usize];
-                    for y in 0..tile::MAX_SIZE {
-                        for x in 0..tile::MAX_SIZE {
-                            if !tile_layout.is_present_at(x, y) {
-                                continue;
-                            }
-                            dead_cells.set_blocked(x + operation.dx, y + operation.dy);
-                        }
-                    }
-                }
-            })
-            .min_by_key(|case_distinction| case_distinction.len())
-            .unwrap();
+        if remaining_tile_cells + dead_cells.count_unblocked() > node.board.count_unblocked() {
+            // Any solution stemming from 'node' would take up more space than exists.
+            return None;
+        }
 
-        if best_case_distinction.len() == 0 {
-            // There is a tile which cannot be placed, therefore we don't need to consider this subtree at all.
+        if must_fill_board && dead_cells.count_unblocked() > 0 {
+            // Some cell cannot be covered at all.
             return None;
         }
-        if remaining_tile_cells + dead_cells.count_unblocked() > node.board.count_unblocked() {
-            // Any solution stemming from 'node' would take up more space than exists.
+
+        let mut live_cells = node.board.clone();
+        live_cells.block(&dead_cells);
+        if self.all_tiles_connected {
+            let waste = count_region_waste(&live_cells, &self.tiles, &remaining.counts);
+            let slack =
+                node.board.count_unblocked() - dead_cells.count_unblocked() - remaining_tile_cells;
+            if waste > slack || (must_fill_board && waste > 0) {
+                // Some regions are too small, or cannot be filled up exactly.
+                return None;
+            }
+        }
+        let is_root = node.operation.is_none();
+        if !self
+            .parity
+            .is_possible(&live_cells, &remaining.counts, is_root)
+        {
+            // The colors of the remaining cells cannot be balanced by the remaining tiles.
             return None;
         }
+        // Without slack, every cell that can be covered must be covered.
+        let zero_slack =
+            remaining_tile_cells + dead_cells.count_unblocked() == node.board.count_unblocked();
+        let candidate_counts: Vec<Count> = tile_indices
+            .iter()
+            .map(|&tile_index| remaining.counts[tile_index])
+            .collect();
+        if zero_slack && !propagate_forced(&live_cells, &candidate_counts, &mut case_distinctions) {
+            // Some cell can no longer be covered, or some tile can no longer be placed.
+            return None;
+        }
+        // With identical copies, only the tile-by-tile order of 'last_operations' avoids
+        // permutations, so cells can only be branched on if there are none.
+        let no_copies = candidate_counts
+            .iter()
+            .all(|&count| count == Count::Limited(1));
+        let candidates = Candidates {
+            board: &node.board,
+            tiles: &self.tiles,
+            tile_indices,
+            children: &case_distinctions,
+            can_branch_on_cell: must_fill_board || (zero_slack && no_copies),
+            must_branch_on_cell: must_fill_board,
+        };
+        let mut best_case_distinction = match self.heuristic.choose(&candidates) {
+            Branch::Tile(candidate) => {
+                assert!(
+                    !must_fill_board,
+                    "{:?} must branch on a cell",
+                    self.heuristic
+                );
+                case_distinctions.swap_remove(candidate)
+            }
+            Branch::Cell(x, y) => {
+                assert!(
+                    candidates.can_branch_on_cell,
+                    "{:?} must branch on a tile",
+                    self.heuristic
+                );
+                // Every solution covers this cell exactly once.
+                case_distinctions
+                    .into_iter()
+                    .flatten()
+                    .filter(|child| child.board.is_blocked_at(x, y))
+                    .collect()
+            }
+        };
+        // 'Frame::pending' is a stack, so the highest priority must come last.
+        best_case_distinction.sort_by_key(|child| {
+            let operation = child.operation();
+            let IndexedTileLayout {
+                tile_index,
+                layout_index,
+            } = operation.indexed_tile_layout;
+            let layout =
+                &self.tiles[usize::from(tile_index)].get_layouts()[usize::from(layout_index)];
+            self.heuristic
+                .priority(&node.board, layout, operation.dx, operation.dy)
+        });
+        Some(best_case_distinction)
+    }
 
-        self.closed.push(node); // Only now 'next_parent_index' becomes actually valid!
+    fn transposition_key(&self, board: &Board, remaining: &Remaining) -> Option<TranspositionKey> {
+        if self.max_failed == 0 {
+            return None;
+        }
+        let must_fill_board = remaining.counts.contains(&Count::Unlimited);
+        let last_operations = remaining
+            .counts
+            .iter()
+            .zip(&remaining.last_operations)
+            .map(|(count, last_operation)| match count {
+                Count::Limited(1..) if !must_fill_board => last_operation.clone(),
+                _ => None,
+            })
+            .collect();
+        Some(TranspositionKey {
+            board: board.clone(),
+            counts: remaining.counts.clone(),
+            last_operations,
+        })
+    }
 
-        self.open.extend(best_case_distinction);
+    // Forgetting everything is crude, but cheap, and recent failures are rebuilt quickly.
+    fn record_failure(&mut self, key: TranspositionKey) {
+        if self.failed.len() >= self.max_failed {
+            self.failed.clear();
+        }
+        self.failed.insert(key);
+    }
 
-        // If any of the children are a solution, then *all* children are solutions,
-        // so it's okay to delay by one step.
-        None
+    // Drops the frames whose children have all been explored. Those without a solution are
+    // recorded as failures.
+    fn pop_finished_frames(&mut self) {
+        while self
+            .frames
+            .last()
+            .is_some_and(|frame| frame.pending.is_empty())
+        {
+            let frame = self.frames.pop().unwrap();
+            if frame.has_solution {
+                if let Some(parent) = self.frames.last_mut() {
+                    parent.has_solution = true;
+                }
+            } else if let Some(key) = frame.key {
+                self.record_failure(key);
+            }
+        }
+    }
+
+    /* A compact, single-line snapshot of the search, for example:
+     *
+     *     mebongo-search 1;3x3;1ff;1,1,1;-/0/;-/0/0.0.0.0,0.0.1.0,0.0.0.1;0.0.1.1/0/2.0.0.0,2.1.0.0
+     *
+     * That is the header, the initial board (see 'Board::to_encoded', in hexadecimal), the
+     * counts ('inf' if unlimited), and then one entry per frame: The operation, whether a
+     * solution has been found below it, and the pending children. Operations are written as
+     * 'tile_index.layout_index.dx.dy', or '-' for the root.
+     * The tiles themselves, the heuristic and the transposition table are not included. With the
+     * same tiles and heuristic, a resumed search finds the same solutions in the same order, but
+     * might take a few more steps, since it has to rediscover the known failures.
+     */
+    pub fn to_checkpoint(&self) -> String {
+        let encode_operation = |operation: Option<&Operation>| match operation {
+            Some(operation) => format!(
+                "{}.{}.{}.{}",
+                operation.indexed_tile_layout.tile_index,
+                operation.indexed_tile_layout.layout_index,
+                operation.dx,
+                operation.dy
+            ),
+            None => "-".to_string(),
+        };
+        let mut fields = vec![
+            format!("{} {}", CHECKPOINT_HEADER, CHECKPOINT_VERSION),
+            format!(
+                "{}x{}",
+                self.initial_board.width(),
+                self.initial_board.height()
+            ),
+        ];
+        let words = self.initial_board.to_encoded();
+        let words: Vec<String> = words.iter().map(|word| format!("{:x}", word)).collect();
+        fields.push(words.join(","));
+        let counts: Vec<String> = self
+            .counts
+            .iter()
+            .map(|count| match count {
+                Count::Limited(count) => count.to_string(),
+                Count::Unlimited => "inf".to_string(),
+            })
+            .collect();
+        fields.push(counts.join(","));
+        for frame in &self.frames {
+            let pending: Vec<String> = frame
+                .pending
+                .iter()
+                .map(|node| encode_operation(node.operation.as_ref()))
+                .collect();
+            fields.push(format!(
+                "{}/{}/{}",
+                encode_operation(frame.operation.as_ref()),
+                u8::from(frame.has_solution),
+                pending.join(",")
+            ));
+        }
+        fields.join(";")
+    }
+
+    // Inverse of 'to_checkpoint', given the same tiles.
+    pub fn from_checkpoint(
+        checkpoint: &str,
+        tiles: &[Tile],
+    ) -> std::result::Result<Self, CheckpointError> {
+        let mut fields = checkpoint.trim().split(';');
+        let version = fields
+            .next()
+            .and_then(|header| header.strip_prefix(CHECKPOINT_HEADER))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or(CheckpointError::MissingHeader)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+        let mut next_field = || fields.next().ok_or(CheckpointError::Syntax);
+        let (width, height) = next_field()?
+            .split_once('x')
+            .and_then(|(width, height)| {
+                Some((width.parse::<u8>().ok()?, height.parse::<u8>().ok()?))
+            })
+            .ok_or(CheckpointError::Syntax)?;
+        let board_size = u32::from(width) * u32::from(height);
+        if board_size == 0 || board_size > MAX_CELLS {
+            return Err(CheckpointError::Syntax);
+        }
+        let words = next_field()?
+            .split(',')
+            .map(|word| u32::from_str_radix(word, 16))
+            .collect::<std::result::Result<Vec<u32>, _>>()
+            .map_err(|_| CheckpointError::Syntax)?;
+        let num_words = board_size.div_ceil(u32::BITS) as usize;
+        let unused_bits = num_words as u32 * u32::BITS - board_size;
+        if words.len() != num_words || words[num_words - 1].leading_zeros() < unused_bits {
+            return Err(CheckpointError::Syntax);
+        }
+        let initial_board = Board::from_encoded(width, height, &words);
+        let counts = next_field()?
+            .split(',')
+            .map(|count| match count {
+                "inf" => Ok(Count::Unlimited),
+                count => count.parse().map(Count::Limited),
+            })
+            .collect::<std::result::Result<Vec<Count>, _>>()
+            .map_err(|_| CheckpointError::Syntax)?;
+        if counts.len() != tiles.len() {
+            return Err(CheckpointError::Mismatch);
+        }
+
+        let mut state = State::with_counts(initial_board.clone(), tiles, counts);
+        let root_frame = state.frames.pop().unwrap();
+        let mut board = initial_board;
+        let mut remaining = root_frame.remaining;
+        // Checks and applies an operation to the board and the remaining tiles.
+        let apply = |operation: Option<&Operation>, board: &Board, remaining: &Remaining| {
+            let Some(operation) = operation else {
+                return Ok((board.clone(), remaining.clone()));
+            };
+            let tile_index = usize::from(operation.indexed_tile_layout.tile_index);
+            let layout_index = usize::from(operation.indexed_tile_layout.layout_index);
+            let layout = tiles
+                .get(tile_index)
+                .and_then(|tile| tile.get_layouts().get(layout_index))
+                .ok_or(CheckpointError::Mismatch)?;
+            if operation.dx >= board.width() || operation.dy >= board.height() {
+                return Err(CheckpointError::Mismatch);
+            }
+            let child_board = board
+                .tile_mask(layout, operation.dx, operation.dy)
+                .filter(|mask| board.contains(mask))
+                .map(|mask| {
+                    let mut child_board = board.clone();
+                    child_board.block(&mask);
+                    child_board
+                })
+                .ok_or(CheckpointError::Mismatch)?;
+            if remaining.counts[tile_index] == Count::Limited(0) {
+                return Err(CheckpointError::Mismatch);
+            }
+            Ok((child_board, remaining.after(Some(operation))))
+        };
+        for (frame_index, field) in fields.enumerate() {
+            let mut parts = field.split('/');
+            let (Some(operation), Some(has_solution), Some(pending), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Err(CheckpointError::Syntax);
+            };
+            let operation = decode_operation(operation)?;
+            let has_solution = match has_solution {
+                "0" => false,
+                "1" => true,
+                _ => return Err(CheckpointError::Syntax),
+            };
+            // The first frame is the one below the root, the second one is the root itself.
+            if operation.is_none() != (frame_index <= 1) {
+                return Err(CheckpointError::Mismatch);
+            }
+            (board, remaining) = apply(operation.as_ref(), &board, &remaining)?;
+            let key = if frame_index > 0 {
+                state.transposition_key(&board, &remaining)
+            } else {
+                None
+            };
+            let mut children = Vec::new();
+            for child in pending.split(',').filter(|child| !child.is_empty()) {
+                let operation = decode_operation(child)?;
+                // There is only one root.
+                if operation.is_none() != (frame_index == 0)
+                    || (frame_index == 0 && !children.is_empty())
+                {
+                    return Err(CheckpointError::Mismatch);
+                }
+                let (child_board, _) = apply(operation.as_ref(), &board, &remaining)?;
+                children.push(Node {
+                    board: child_board,
+                    operation,
+                });
+            }
+            state.frames.push(Frame {
+                operation,
+                remaining: remaining.clone(),
+                pending: children,
+                has_solution,
+                key,
+            });
+        }
+        if state
+            .frames
+            .last()
+            .is_some_and(|frame| frame.pending.is_empty())
+        {
+            return Err(CheckpointError::Syntax);
+        }
+        Ok(state)
     }
 
     pub fn step_at_most(&mut self, max_steps: usize) -> (usize, Option<Result>) {
@@ -205,18 +677,173 @@ impl<'a> State<'a> {
 
         (max_steps, None)
     }
+
+    pub fn solutions(&mut self, max_steps: usize) -> Solutions<'_, 'a> {
+        Solutions {
+            state: self,
+            max_steps,
+            steps_taken: 0,
+            budget_used: 0,
+        }
+    }
+}
+
+// Keeps searching after each solution, until the tree is exhausted or `max_steps` is used up.
+// Each solution is yielded exactly once: Every node branches either on the placements of a single
+// tile, or on the placements covering a single cell, so the subtrees of two siblings never overlap
+// (as long as the layouts of each tile are unique).
+#[derive(Debug)]
+pub struct Solutions<'s, 'a> {
+    state: &'s mut State<'a>,
+    max_steps: usize,
+    steps_taken: usize,
+    // Like 'steps_taken', but including the steps that yielded a solution, which
+    // 'State::step_at_most' does not count. Otherwise, each solution would extend the budget.
+    budget_used: usize,
+}
+
+impl Solutions<'_, '_> {
+    pub fn steps_taken(&self) -> usize {
+        self.steps_taken
+    }
+
+    pub fn has_finished(&self) -> bool {
+        !self.state.can_step()
+    }
+}
+
+impl Iterator for Solutions<'_, '_> {
+    type Item = Result;
+
+    fn next(&mut self) -> Option<Result> {
+        let (steps_done, result_maybe) = self.state.step_at_most(self.max_steps - self.budget_used);
+        self.steps_taken += steps_done;
+        self.budget_used += steps_done + usize::from(result_maybe.is_some());
+        result_maybe
+    }
+}
+
+// See 'State::to_checkpoint'.
+fn decode_operation(text: &str) -> std::result::Result<Option<Operation>, CheckpointError> {
+    if text == "-" {
+        return Ok(None);
+    }
+    let numbers = text
+        .split('.')
+        .map(str::parse::<u8>)
+        .collect::<std::result::Result<Vec<u8>, _>>()
+        .map_err(|_| CheckpointError::Syntax)?;
+    match numbers[..] {
+        [tile_index, layout_index, dx, dy] => {
+            Ok(Some(Operation::from(tile_index, layout_index, dx, dy)))
+        }
+        _ => Err(CheckpointError::Syntax),
+    }
+}
+
+// Without slack, each of the 'live_cells' must be covered by exactly one of the placements, with
+// one placement per copy of a tile. A cell that only one tile can cover restricts that tile to the
+// placements covering it, and a tile with only one placement left rules out all placements of
+// the other tiles that overlap it. Both only work for tiles with a single copy left, since the
+// other copies could go anywhere else. Repeats until nothing changes, and returns false as soon
+// as a cell or tile runs out of placements. 'counts' is parallel to 'case_distinctions'.
+fn propagate_forced(
+    live_cells: &Board,
+    counts: &[Count],
+    case_distinctions: &mut [Vec<Node>],
+) -> bool {
+    let cells: Vec<(u8, u8)> = (0..live_cells.height())
+        .flat_map(|y| (0..live_cells.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| !live_cells.is_blocked_at(x, y))
+        .collect();
+    let covers = |child: &Node, (x, y): (u8, u8)| child.board.is_blocked_at(x, y);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &cell in &cells {
+            let mut covering_tiles = case_distinctions
+                .iter()
+                .enumerate()
+                .filter(|(_, children)| children.iter().any(|child| covers(child, cell)))
+                .map(|(i, _)| i);
+            let Some(only) = covering_tiles.next() else {
+                return false;
+            };
+            if covering_tiles.next().is_none() && counts[only] == Count::Limited(1) {
+                let children = &mut case_distinctions[only];
+                let before = children.len();
+                children.retain(|child| covers(child, cell));
+                changed |= children.len() != before;
+            }
+        }
+        for forced in 0..case_distinctions.len() {
+            if case_distinctions[forced].len() != 1 || counts[forced] != Count::Limited(1) {
+                continue;
+            }
+            let forced_cells: Vec<(u8, u8)> = cells
+                .iter()
+                .copied()
+                .filter(|&cell| covers(&case_distinctions[forced][0], cell))
+                .collect();
+            for (other, children) in case_distinctions.iter_mut().enumerate() {
+                if other == forced {
+                    continue;
+                }
+                let before = children.len();
+                children.retain(|child| !forced_cells.iter().any(|&cell| covers(child, cell)));
+                if children.is_empty() && counts[other] != Count::Unlimited {
+                    return false;
+                }
+                changed |= children.len() != before;
+            }
+        }
+    }
+    true
+}
+
+// Every tile lies within a single region, so the tiles within a region add up to at most its
+// size, but usually not exactly. This is how many cells stay uncovered at the very least.
+fn count_region_waste(board: &Board, tiles: &[Tile], counts: &[Count]) -> u32 {
+    let region_sizes = board.region_sizes();
+    let largest = region_sizes.iter().copied().max().unwrap_or(0) as usize;
+    // 'reachable[n]' iff some selection of the remaining tiles has exactly 'n' cells.
+    let mut reachable = vec![false; largest + 1];
+    reachable[0] = true;
+    for (tile, &count) in tiles.iter().zip(counts) {
+        let size = tile.get_size() as usize;
+        let copies = match count {
+            Count::Limited(count) => usize::from(count).min(largest / size),
+            Count::Unlimited => largest / size,
+        };
+        for _ in 0..copies {
+            for n in (size..=largest).rev() {
+                reachable[n] |= reachable[n - size];
+            }
+        }
+    }
+    region_sizes
+        .iter()
+        .map(|&region_size| {
+            let filled = (0..=region_size)
+                .rev()
+                .find(|&n| reachable[n as usize])
+                .unwrap();
+            region_size - filled
+        })
+        .sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tile;
 
     #[test]
     fn test_basic_negative() {
         let tiles: Vec<_> = vec![Tile::new_for_test(vec![0x0001])];
-        let mut s = State::new(Board::all_blocked(), &tiles);
-        assert_eq!(s.closed.len(), 0);
-        assert_eq!(s.open.len(), 1);
+        let mut s = State::new(Board::all_blocked(5, 6), &tiles);
+        assert_eq!(s.frames.len(), 1);
+        assert_eq!(s.frames[0].pending.len(), 1);
         assert!(s.can_step());
         assert_eq!(s.step_single(), None);
         assert!(!s.can_step());
@@ -225,11 +852,11 @@ mod tests {
     #[test]
     fn test_basic_positive() {
         let tiles: Vec<_> = vec![Tile::new_for_test(vec![0x8000, 0x0001])];
-        let mut board = Board::all_blocked();
+        let mut board = Board::all_blocked(5, 6);
         board.set_unblocked(2, 3);
         let mut s = State::new(board, &tiles);
-        assert_eq!(s.closed.len(), 0);
-        assert_eq!(s.open.len(), 1);
+        assert_eq!(s.frames.len(), 1);
+        assert_eq!(s.frames[0].pending.len(), 1);
         assert!(s.can_step());
         assert_eq!(s.step_single(), None);
         assert_eq!(s.step_single(), Some(vec![Operation::from(0, 1, 2, 3)]));
@@ -239,20 +866,12 @@ mod tests {
     #[test]
     fn test_trivial_positive() {
         let tiles: Vec<_> = vec![Tile::new_for_test(vec![0x0000])];
-        let mut s = State::new(Board::all_blocked(), &tiles);
-        assert_eq!(s.closed.len(), 0);
-        assert_eq!(s.open.len(), 1);
+        let mut s = State::new(Board::all_blocked(5, 6), &tiles);
+        assert_eq!(s.frames.len(), 1);
+        assert_eq!(s.frames[0].pending.len(), 1);
         assert!(s.can_step());
         assert_eq!(s.step_single(), None);
-        assert_eq!(
-            s.step_single(),
-            Some(vec![Operation::from(
-                0,
-                0,
-                board::MAX_WIDTH - 1,
-                board::MAX_HEIGHT - 1
-            )])
-        );
+        assert_eq!(s.step_single(), Some(vec![Operation::from(0, 0, 4, 5)]));
         // Will generate many more solutions, one for each possible offset.
         assert!(s.can_step());
     }
@@ -260,9 +879,9 @@ mod tests {
     #[test]
     fn test_notiles_positive() {
         let tiles: Vec<_> = vec![];
-        let mut s = State::new(Board::all_blocked(), &tiles);
-        assert_eq!(s.closed.len(), 0);
-        assert_eq!(s.open.len(), 1);
+        let mut s = State::new(Board::all_blocked(5, 6), &tiles);
+        assert_eq!(s.frames.len(), 1);
+        assert_eq!(s.frames[0].pending.len(), 1);
         assert!(s.can_step());
         assert_eq!(s.step_single(), Some(vec![]));
         assert!(!s.can_step());
@@ -271,7 +890,7 @@ mod tests {
     #[test]
     fn test_basic_positive_multi() {
         let tiles: Vec<_> = vec![Tile::new_for_test(vec![0x8000, 0x0001])];
-        let mut board = Board::all_blocked();
+        let mut board = Board::all_blocked(5, 6);
         board.set_unblocked(2, 3);
         let mut s = State::new(board, &tiles);
         assert_eq!(
@@ -291,7 +910,7 @@ mod tests {
             Tile::new_for_test(vec![0x0011]),
             Tile::new_for_test(vec![0x0311]),
         ];
-        let mut board = Board::all_blocked();
+        let mut board = Board::all_blocked(5, 6);
         board.set_unblocked(3, 0);
         board.set_unblocked(3, 1);
         board.set_unblocked(3, 2);
@@ -299,13 +918,16 @@ mod tests {
         board.set_unblocked(3, 4);
         board.set_unblocked(4, 4);
         let mut s = State::new(board, &tiles);
+        // Without slack, both tiles are left with a single placement, and the tie goes to the
+        // first tile. The result starts at the leaf, so the domino, which is placed first, comes
+        // last.
         assert_eq!(
             s.step_at_most(5),
             (
                 2,
                 Some(vec![
-                    Operation::from(0, 0, 3, 0),
                     Operation::from(1, 0, 3, 2),
+                    Operation::from(0, 0, 3, 0),
                 ])
             )
         );
@@ -322,7 +944,7 @@ mod tests {
             tile::ALL_TILES[6].clone(),  // S shape
             tile::ALL_TILES[10].clone(), // elongated three-way pipe
         ];
-        let mut board = Board::all_blocked();
+        let mut board = Board::all_blocked(5, 6);
         board.set_unblocked(1, 0);
         board.set_unblocked(2, 0);
         board.set_unblocked(0, 1);
@@ -354,7 +976,7 @@ mod tests {
                 ])
             )
         );
-        // There is more than one solution
+        // The search is not done yet, even though this is the only solution.
         assert!(s.can_step());
     }
 
@@ -365,7 +987,7 @@ mod tests {
             tile::ALL_TILES[6].clone(),  // S shape
             tile::ALL_TILES[10].clone(), // elongated three-way pipe
         ];
-        let mut board = Board::all_blocked();
+        let mut board = Board::all_blocked(5, 6);
         board.set_unblocked(1, 0);
         board.set_unblocked(2, 0);
         board.set_unblocked(0, 1);
@@ -384,4 +1006,506 @@ mod tests {
         assert_eq!(s.step_at_most(1000), (15, None));
         assert!(!s.can_step());
     }
+
+    #[test]
+    fn test_solutions_twotile() {
+        let tiles: Vec<_> = vec![
+            Tile::new_for_test(vec![0x0011]),
+            Tile::new_for_test(vec![0x0311]),
+        ];
+        let mut board = Board::all_blocked(5, 6);
+        board.set_unblocked(3, 0);
+        board.set_unblocked(3, 1);
+        board.set_unblocked(3, 2);
+        board.set_unblocked(3, 3);
+        board.set_unblocked(3, 4);
+        board.set_unblocked(4, 4);
+        let mut s = State::new(board, &tiles);
+        let mut solutions = s.solutions(100);
+        assert_eq!(
+            solutions.next(),
+            Some(vec![
+                Operation::from(1, 0, 3, 2),
+                Operation::from(0, 0, 3, 0),
+            ])
+        );
+        assert_eq!(solutions.next(), None);
+        assert_eq!(solutions.steps_taken(), 2);
+        assert!(solutions.has_finished());
+    }
+
+    #[test]
+    fn test_solutions_sample_easy() {
+        let tiles: Vec<_> = vec![
+            tile::ALL_TILES[5].clone(),  // three-way pipe
+            tile::ALL_TILES[6].clone(),  // S shape
+            tile::ALL_TILES[10].clone(), // elongated three-way pipe
+        ];
+        let mut board = Board::all_blocked(5, 6);
+        board.set_unblocked(1, 0);
+        board.set_unblocked(2, 0);
+        board.set_unblocked(0, 1);
+        board.set_unblocked(1, 1);
+        board.set_unblocked(2, 1);
+        board.set_unblocked(3, 1);
+        board.set_unblocked(1, 2);
+        board.set_unblocked(2, 2);
+        board.set_unblocked(3, 2);
+        board.set_unblocked(4, 2);
+        board.set_unblocked(1, 3);
+        board.set_unblocked(2, 3);
+        board.set_unblocked(3, 3);
+        let mut s = State::new(board, &tiles);
+        let mut solutions = s.solutions(1000);
+        let all = solutions.by_ref().collect::<Vec<_>>();
+        assert!(solutions.has_finished());
+        assert_eq!(solutions.steps_taken(), 21);
+        assert_eq!(all.len(), 1);
+        assert_eq!(
+            all[0],
+            vec![
+                Operation::from(0, 1, 2, 0),
+                Operation::from(1, 1, 2, 2),
+                Operation::from(2, 4, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_solutions_budget_is_exact() {
+        // Unlimited dominoes tile a 2×4 rectangle in 5 ways.
+        let tiles = vec![tile::ALL_TILES[1].clone()];
+        let board: Board = "XXXX\nXXXX".parse().unwrap();
+        let new_state = || State::with_counts(board.clone(), &tiles, vec![Count::Unlimited]);
+        let mut total_steps = 0;
+        let mut s = new_state();
+        while s.can_step() {
+            s.step_single();
+            total_steps += 1;
+        }
+        // Even if the budget runs out right after a solution, no extra step is taken.
+        for max_steps in 0..total_steps {
+            let mut by_solutions = new_state();
+            let mut solutions = by_solutions.solutions(max_steps);
+            let num_solutions = solutions.by_ref().count();
+            assert_eq!(solutions.steps_taken() + num_solutions, max_steps);
+            let mut by_single_steps = new_state();
+            for _ in 0..max_steps {
+                by_single_steps.step_single();
+            }
+            assert_eq!(
+                by_solutions.to_checkpoint(),
+                by_single_steps.to_checkpoint()
+            );
+        }
+        let mut s = new_state();
+        assert_eq!(s.solutions(total_steps).count(), 5);
+    }
+
+    #[test]
+    fn test_solutions_budget() {
+        let tiles: Vec<_> = vec![Tile::new_for_test(vec![0x0000])];
+        let mut s = State::new(Board::all_blocked(5, 6), &tiles);
+        // The first step expands the root, after that the budget is used up:
+        let mut solutions = s.solutions(1);
+        assert_eq!(solutions.next(), None);
+        assert_eq!(solutions.steps_taken(), 1);
+        assert!(!solutions.has_finished());
+        // Continue where we left off; one solution for each possible offset.
+        let mut solutions = s.solutions(100);
+        assert_eq!(solutions.by_ref().count(), 5 * 6);
+        assert!(solutions.has_finished());
+    }
+
+    #[test]
+    fn test_large_board() {
+        let tiles: Vec<_> = vec![
+            tile::ALL_TILES[8].clone(),  // "L" shape
+            tile::ALL_TILES[11].clone(), // big "P" shape
+        ];
+        // 9×10 board, but only a 3×3 square in the bottom right corner is available:
+        let mut board = Board::all_blocked(9, 10);
+        for y in 7..10 {
+            for x in 6..9 {
+                board.set_unblocked(x, y);
+            }
+        }
+        let mut s = State::new(board, &tiles);
+        let mut solutions = s.solutions(1000);
+        let all = solutions.by_ref().collect::<Vec<_>>();
+        assert!(solutions.has_finished());
+        // The 2×2 part of the "P" goes into one of the four corners, pointing either way.
+        assert_eq!(all.len(), 8);
+        assert!(all
+            .iter()
+            .all(|r| r.iter().all(|op| op.dx >= 6 && op.dy >= 7)));
+    }
+
+    #[test]
+    fn test_large_tiles() {
+        let bar = (0..6).map(|x| (x, 0)).collect::<Vec<_>>();
+        let bar = Tile::from_shape(&tile::TileLayout::from_cells(&bar));
+        let tiles: Vec<_> = vec![bar.clone(), bar];
+        let mut board = Board::all_blocked(6, 7);
+        for x in 0..6 {
+            board.set_unblocked(x, 5);
+            board.set_unblocked(x, 6);
+        }
+        let mut s = State::new(board, &tiles);
+        let mut solutions = s.solutions(100);
+        assert_eq!(
+            solutions.next(),
+            Some(vec![
+                Operation::from(1, 0, 0, 5),
+                Operation::from(0, 0, 0, 6)
+            ])
+        );
+        // Both tiles are the same, so they can be swapped:
+        assert_eq!(
+            solutions.next(),
+            Some(vec![
+                Operation::from(1, 0, 0, 6),
+                Operation::from(0, 0, 0, 5)
+            ])
+        );
+        assert_eq!(solutions.next(), None);
+        assert!(solutions.has_finished());
+    }
+
+    #[test]
+    fn test_copies_are_not_permuted() {
+        // Same as test_large_tiles, but with two copies of the same tile.
+        let bar = (0..6).map(|x| (x, 0)).collect::<Vec<_>>();
+        let tiles: Vec<_> = vec![Tile::from_shape(&tile::TileLayout::from_cells(&bar))];
+        let mut board = Board::all_blocked(6, 7);
+        for x in 0..6 {
+            board.set_unblocked(x, 5);
+            board.set_unblocked(x, 6);
+        }
+        let mut s = State::with_counts(board, &tiles, vec![Count::Limited(2)]);
+        let mut solutions = s.solutions(100);
+        assert_eq!(
+            solutions.next(),
+            Some(vec![
+                Operation::from(0, 0, 0, 6),
+                Operation::from(0, 0, 0, 5)
+            ])
+        );
+        assert_eq!(solutions.next(), None);
+        assert!(solutions.has_finished());
+    }
+
+    #[test]
+    fn test_copies_mixed() {
+        // Two single cells and one domino on a 2×2 square: The domino goes into one of the four
+        // possible places, and the two single cells fill up the rest in only one way.
+        let tiles: Vec<_> = vec![
+            Tile::new_for_test(vec![0x0001]),
+            Tile::new_for_test(vec![0x0003, 0x0011]),
+        ];
+        let mut board = Board::all_blocked(5, 6);
+        for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+            board.set_unblocked(x, y);
+        }
+        let mut s = State::with_counts(board, &tiles, vec![Count::Limited(2), Count::Limited(1)]);
+        let mut solutions = s.solutions(1000);
+        assert_eq!(solutions.by_ref().count(), 4);
+        assert!(solutions.has_finished());
+    }
+
+    #[test]
+    fn test_unlimited_tiling() {
+        // There are 8 ways to tile a 2×5 rectangle with dominoes (Fibonacci).
+        let tiles: Vec<_> = vec![Tile::new_for_test(vec![0x0003, 0x0011])];
+        let mut board = Board::all_blocked(5, 6);
+        for x in 0..5 {
+            board.set_unblocked(x, 2);
+            board.set_unblocked(x, 3);
+        }
+        let mut s = State::with_counts(board, &tiles, vec![Count::Unlimited]);
+        let mut solutions = s.solutions(1000);
+        let all = solutions.by_ref().collect::<Vec<_>>();
+        assert!(solutions.has_finished());
+        assert_eq!(all.len(), 8);
+        assert!(all.iter().all(|r| r.len() == 5));
+    }
+
+    #[test]
+    fn test_unlimited_with_limited() {
+        // A 1×3 bar must be used exactly once, the rest of the 3×3 square is tiled with single
+        // cells. The bar can go into any of the three rows or columns.
+        let tiles: Vec<_> = vec![
+            Tile::new_for_test(vec![0x0007, 0x0111]),
+            Tile::new_for_test(vec![0x0001]),
+        ];
+        let mut board = Board::all_blocked(5, 6);
+        for y in 0..3 {
+            for x in 0..3 {
+                board.set_unblocked(x, y);
+            }
+        }
+        let mut s = State::with_counts(board, &tiles, vec![Count::Limited(1), Count::Unlimited]);
+        let mut solutions = s.solutions(1000);
+        assert_eq!(solutions.by_ref().count(), 6);
+        assert!(solutions.has_finished());
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let tiles = crate::catalog::find("pentominoes").unwrap().get_tiles();
+        let mut counts = vec![Count::Limited(0); tiles.len()];
+        for name in ["L", "P", "U", "Y"] {
+            counts[tiles.iter().position(|t| t.get_name() == name).unwrap()] = Count::Limited(1);
+        }
+        let board: Board = "XXXXX\nXXXXX\nXXXXX\nXXXXX\nX....".parse().unwrap();
+        let mut s = State::with_counts(board.clone(), tiles, counts.clone());
+        let all: Vec<Result> = s.solutions(10_000).collect();
+        assert_eq!(all.len(), 23);
+
+        let mut s = State::with_counts(board, tiles, counts);
+        let mut found = Vec::new();
+        while s.can_step() {
+            let (_, result) = s.step_at_most(50);
+            found.extend(result);
+            let checkpoint = s.to_checkpoint();
+            s = State::from_checkpoint(&checkpoint, tiles).unwrap();
+            assert_eq!(s.to_checkpoint(), checkpoint);
+        }
+        assert_eq!(found, all);
+        // Nothing left to explore.
+        assert_eq!(
+            s.to_checkpoint(),
+            "mebongo-search 1;5x5;1fffff;0,0,1,0,1,0,1,0,0,0,1,0"
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_errors() {
+        let tiles = [tile::ALL_TILES[0].clone(), tile::ALL_TILES[1].clone()];
+        let s = State::new("XX\nXX".parse().unwrap(), &tiles);
+        let checkpoint = s.to_checkpoint();
+        assert_eq!(checkpoint, "mebongo-search 1;2x2;f;1,1;-/0/-");
+        assert!(State::from_checkpoint(&checkpoint, &tiles).is_ok());
+        let error = |checkpoint: &str| State::from_checkpoint(checkpoint, &tiles).unwrap_err();
+        assert_eq!(error(""), CheckpointError::MissingHeader);
+        assert_eq!(
+            error("mebongo-search 2;2x2;f;1,1;-/0/-"),
+            CheckpointError::UnsupportedVersion(2)
+        );
+        assert_eq!(error("mebongo-search 1;2x2;f"), CheckpointError::Syntax);
+        assert_eq!(
+            error("mebongo-search 1;2x2;1f;1,1;-/0/-"),
+            CheckpointError::Syntax
+        );
+        assert_eq!(
+            error("mebongo-search 1;2x2;f;1;-/0/-"),
+            CheckpointError::Mismatch
+        );
+        assert_eq!(
+            error("mebongo-search 1;2x2;f;1,1;-/0/"),
+            CheckpointError::Syntax
+        );
+        assert_eq!(
+            error("mebongo-search 1;2x2;f;1,1;-/0/;-/0/0.0.1.0"),
+            CheckpointError::Mismatch
+        );
+        assert_eq!(
+            error("mebongo-search 1;2x2;f;1,1;-/0/;-/0/2.0.0.0"),
+            CheckpointError::Mismatch
+        );
+        assert_eq!(
+            error("mebongo-search 1;2x2;f;1,1;-/0/;-/0/0.0.0.0;0.0.0.0/0/0.0.0.0"),
+            CheckpointError::Mismatch
+        );
+        assert!(
+            State::from_checkpoint("mebongo-search 1;2x2;f;1,1;-/0/;-/0/1.1.1.0", &tiles).is_ok()
+        );
+        let finished = State::from_checkpoint("mebongo-search 1;2x2;f;1,1", &tiles).unwrap();
+        assert!(!finished.can_step());
+    }
+
+    #[test]
+    fn test_memory_is_bounded() {
+        // Only the path to the current node is kept, one frame per placed tile.
+        let tiles = crate::catalog::find("pentominoes").unwrap().get_tiles();
+        let mut counts = vec![Count::Limited(0); tiles.len()];
+        for name in ["F", "L", "U", "V"] {
+            counts[tiles.iter().position(|t| t.get_name() == name).unwrap()] = Count::Limited(1);
+        }
+        let board: Board = "XXXXX\nXXXXX\nXXXXX\nXXXXX\nX....".parse().unwrap();
+        let mut s = State::with_counts(board, tiles, counts);
+        let mut num_steps = 0;
+        while s.can_step() {
+            s.step_single();
+            num_steps += 1;
+            assert!(s.frames.len() <= 1 + 4);
+            assert!(s
+                .frames
+                .last()
+                .is_none_or(|frame| !frame.pending.is_empty()));
+        }
+        assert!(num_steps > 100);
+    }
+
+    #[test]
+    fn test_most_constrained_cell() {
+        use crate::heuristic::MostConstrainedCell;
+        // F, L, U and V tile a 4×5 rectangle in 8 ways (2 up to symmetry).
+        let tiles = crate::catalog::find("pentominoes").unwrap().get_tiles();
+        let mut counts = vec![Count::Limited(0); tiles.len()];
+        for name in ["F", "L", "U", "V"] {
+            counts[tiles.iter().position(|t| t.get_name() == name).unwrap()] = Count::Limited(1);
+        }
+        let board: Board = "XXXXX\nXXXXX\nXXXXX\nXXXXX".parse().unwrap();
+        let mut by_tile = State::with_counts(board.clone(), tiles, counts.clone());
+        let mut by_tile = by_tile.solutions(10_000);
+        assert_eq!(by_tile.by_ref().count(), 8);
+        assert_eq!(by_tile.steps_taken(), 97);
+        let mut by_cell =
+            State::with_counts(board, tiles, counts).with_heuristic(&MostConstrainedCell);
+        let mut by_cell = by_cell.solutions(10_000);
+        assert_eq!(by_cell.by_ref().count(), 8);
+        assert!(by_cell.has_finished());
+        assert_eq!(by_cell.steps_taken(), 55);
+    }
+
+    #[test]
+    fn test_transpositions() {
+        // L, P, U and Y tile a 4×5 rectangle in 4 ways. Some dead ends can be reached by placing
+        // different tiles in different orders, resulting in the same silhouette.
+        let tiles = crate::catalog::find("pentominoes").unwrap().get_tiles();
+        let mut counts = vec![Count::Limited(0); tiles.len()];
+        for name in ["L", "P", "U", "Y"] {
+            counts[tiles.iter().position(|t| t.get_name() == name).unwrap()] = Count::Limited(1);
+        }
+        let board: Board = "XXXXX\nXXXXX\nXXXXX\nXXXXX".parse().unwrap();
+        let mut without =
+            State::with_counts(board.clone(), tiles, counts.clone()).with_max_failed(0);
+        let mut without = without.solutions(10_000);
+        assert_eq!(without.by_ref().count(), 4);
+        assert_eq!(without.steps_taken(), 179);
+        let (board_copy, counts_copy) = (board.clone(), counts.clone());
+        let mut with = State::with_counts(board, tiles, counts);
+        let mut with = with.solutions(10_000);
+        assert_eq!(with.by_ref().count(), 4);
+        assert!(with.has_finished());
+        // Without slack, 'propagate_forced' already rules out those dead ends.
+        assert_eq!(with.steps_taken(), 179);
+        // A tiny table keeps forgetting, but never forgets anything important.
+        let mut tiny = State::with_counts(board_copy, tiles, counts_copy).with_max_failed(1);
+        assert_eq!(tiny.solutions(10_000).count(), 4);
+    }
+
+    #[test]
+    fn test_transpositions_with_slack() {
+        // Same as 'test_transpositions', plus one spare cell, so that nothing is propagated.
+        let tiles = crate::catalog::find("pentominoes").unwrap().get_tiles();
+        let mut counts = vec![Count::Limited(0); tiles.len()];
+        for name in ["L", "P", "U", "Y"] {
+            counts[tiles.iter().position(|t| t.get_name() == name).unwrap()] = Count::Limited(1);
+        }
+        let board: Board = "XXXXX\nXXXXX\nXXXXX\nXXXXX\nX....".parse().unwrap();
+        let mut without =
+            State::with_counts(board.clone(), tiles, counts.clone()).with_max_failed(0);
+        let mut without = without.solutions(10_000);
+        assert_eq!(without.by_ref().count(), 23);
+        assert_eq!(without.steps_taken(), 414);
+        let mut with = State::with_counts(board, tiles, counts);
+        let mut with = with.solutions(10_000);
+        assert_eq!(with.by_ref().count(), 23);
+        assert_eq!(with.steps_taken(), 408);
+    }
+
+    #[test]
+    fn test_zero_slack() {
+        // The straight 4-piece only fits into the top row, which leaves no room for the domino.
+        let tiles = vec![
+            tile::ALL_TILES[3].clone(), // straight 4-piece
+            Tile::from_shape(&"XX".parse().unwrap()),
+        ];
+        let board: Board = "XXXX\nX..X".parse().unwrap();
+        let mut s = State::new(board, &tiles);
+        let mut solutions = s.solutions(10_000);
+        assert_eq!(solutions.next(), None);
+        assert_eq!(solutions.steps_taken(), 1);
+
+        // Only the straight 3-piece can cover the top right cell, which leaves a single place
+        // for the corner.
+        let tiles = vec![
+            tile::ALL_TILES[2].clone(), // straight 3-piece
+            tile::ALL_TILES[4].clone(), // corner
+        ];
+        let board: Board = "XXXX\nXX..".parse().unwrap();
+        let root = Node::new_root(board.clone());
+        let s = State::new(board, &tiles);
+        let remaining = Remaining {
+            counts: vec![Count::Limited(1); 2],
+            last_operations: vec![None; 2],
+        };
+        let children = s.find_children(&root, &remaining).unwrap();
+        assert_eq!(children.len(), 1);
+
+        // Two copies of the domino: The straight 4-piece is still forced into the top row, so
+        // the cells on the left and right can no longer be covered.
+        let tiles = vec![
+            tile::ALL_TILES[3].clone(), // straight 4-piece
+            Tile::from_shape(&"XX".parse().unwrap()),
+        ];
+        let board: Board = "XXXX\nX..X\n.XX.".parse().unwrap();
+        let counts = vec![Count::Limited(1), Count::Limited(2)];
+        let mut s = State::with_counts(board, &tiles, counts);
+        let mut solutions = s.solutions(10_000);
+        assert_eq!(solutions.next(), None);
+        assert_eq!(solutions.steps_taken(), 1);
+
+        // Copies are placed in a fixed order, and the domino that covers the most constrained
+        // cell does not have to come first. So the search must not branch on cells, see
+        // 'Candidates::can_branch_on_cell'.
+        let tiles = vec![Tile::from_shape(&"XX".parse().unwrap())];
+        let board: Board = "X..\nXXX".parse().unwrap();
+        let mut s = State::with_counts(board, &tiles, vec![Count::Limited(2)])
+            .with_heuristic(&crate::heuristic::MostConstrainedCell);
+        assert_eq!(s.solutions(1000).count(), 1);
+    }
+
+    #[test]
+    fn test_region_sizes() {
+        // Both rows have room for 5 cells, but the tiles only add up to 3, 4, 6, 7 or 10.
+        let tiles = vec![
+            tile::ALL_TILES[2].clone(), // straight 3-piece
+            tile::ALL_TILES[3].clone(), // straight 4-piece
+            tile::ALL_TILES[4].clone(), // corner
+        ];
+        let board: Board = "XXXXX\n.....\nXXXXX".parse().unwrap();
+        let mut s = State::new(board.clone(), &tiles);
+        let mut solutions = s.solutions(10_000);
+        assert_eq!(solutions.next(), None);
+        assert_eq!(solutions.steps_taken(), 1);
+
+        let counts = [Count::Limited(1); 3];
+        assert_eq!(count_region_waste(&board, &tiles, &counts), 2);
+        let counts = [Count::Limited(0), Count::Limited(1), Count::Limited(0)];
+        assert_eq!(count_region_waste(&board, &tiles, &counts), 2);
+        // One straight 3-piece and any number of straight 4-pieces still can't make 5.
+        let counts = [Count::Limited(1), Count::Unlimited, Count::Limited(0)];
+        assert_eq!(count_region_waste(&board, &tiles, &counts), 2);
+        let counts = [Count::Unlimited, Count::Limited(0), Count::Limited(0)];
+        assert_eq!(count_region_waste(&board, &tiles, &counts), 4);
+        let counts = [Count::Limited(0), Count::Limited(0), Count::Limited(0)];
+        assert_eq!(count_region_waste(&board, &tiles, &counts), 10);
+    }
+
+    #[test]
+    fn test_parity() {
+        // On a checkerboard, each T-shaped piece covers 3 cells of one color and 1 of the other,
+        // so an odd number of them can never cover a balanced board.
+        let tiles = vec![tile::ALL_TILES[5].clone()];
+        let board: Board = "XXXXX\nXXXXX\nXXXXX\nXXXXX".parse().unwrap();
+        for count in [Count::Limited(5), Count::Unlimited] {
+            let mut s = State::with_counts(board.clone(), &tiles, vec![count]);
+            let mut solutions = s.solutions(10_000);
+            assert_eq!(solutions.next(), None);
+            assert_eq!(solutions.steps_taken(), 1);
+        }
+    }
 }
@@ -0,0 +1,505 @@
+//! The library entry point: Configure a [`Solver`] once, then solve any number of boards with it.
+//!
+//! ```
+//! use mebongo::{Board, Count, Outcome, Solver};
+//!
+//! let catalog = mebongo::catalog::find("ubongo").unwrap();
+//! let board: Board = "·XX··\nXXXX·\n·XXXX\n·XXX·".parse().unwrap();
+//! let counts = catalog
+//!     .get_tiles()
+//!     .iter()
+//!     .map(|tile| match tile.get_name() {
+//!         "F" | "G" | "L" => Count::Limited(1),
+//!         _ => Count::Limited(0),
+//!     })
+//!     .collect();
+//! let solver = Solver::new(catalog.get_tiles()).counts(counts);
+//! match solver.solve(&board) {
+//!     Outcome::Solved { placements, .. } => assert_eq!(placements.len(), 3),
+//!     outcome => panic!("unexpected {:?}", outcome),
+//! }
+//! ```
+
+use crate::ascii;
+use crate::board::Board;
+use crate::catalog::MAX_TILES;
+use crate::dlx::{self, Solutions};
+use crate::heuristic::{FewestPlacements, Heuristic};
+use crate::puzzle::DEFAULT_MAX_STEPS;
+use crate::search::{self, CheckpointError, Operation};
+use crate::tile::{Count, Orientation, Tile, TileLayout, Transform};
+use std::sync::Arc;
+
+/// One piece of a solution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    tile_index: usize,
+    // Index into the layouts of the tile after applying the orientation rule.
+    layout_index: u8,
+    layout: TileLayout,
+    transform: Transform,
+    x: u8,
+    y: u8,
+}
+
+impl Placement {
+    /// Index of the tile in the slice that was given to [`Solver::new`].
+    pub fn get_tile_index(&self) -> usize {
+        self.tile_index
+    }
+
+    /// How the tile was turned, relative to its first layout.
+    pub fn get_transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// The shape as placed, relative to [`Placement::get_offset`].
+    pub fn get_layout(&self) -> &TileLayout {
+        &self.layout
+    }
+
+    /// The board coordinates of the top-left corner of the layout.
+    pub fn get_offset(&self) -> (u8, u8) {
+        (self.x, self.y)
+    }
+
+    /// The board coordinates of all cells covered by this piece.
+    pub fn cells(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.layout.cells().map(|(x, y)| (x + self.x, y + self.y))
+    }
+
+    // 'tiles' as given to the search, i.e. after applying the orientation rules.
+    pub(crate) fn from_operation(tiles: &[Tile], operation: &Operation) -> Placement {
+        let tile_index = usize::from(operation.indexed_tile_layout.tile_index);
+        let layout_index = operation.indexed_tile_layout.layout_index;
+        let tile = &tiles[tile_index];
+        Placement {
+            tile_index,
+            layout_index,
+            layout: tile.get_layouts()[usize::from(layout_index)].clone(),
+            transform: tile.get_transform(layout_index),
+            x: operation.dx,
+            y: operation.dy,
+        }
+    }
+
+    fn to_operation(&self) -> Operation {
+        Operation {
+            indexed_tile_layout: search::IndexedTileLayout {
+                tile_index: self.tile_index as u8,
+                layout_index: self.layout_index,
+            },
+            dx: self.x,
+            dy: self.y,
+        }
+    }
+}
+
+/// The result of [`Solver::solve`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The pieces in the order in which they were placed.
+    Solved {
+        placements: Vec<Placement>,
+        steps_taken: usize,
+    },
+    /// The search was exhausted without finding any solution.
+    Impossible { steps_taken: usize },
+    /// The step budget ran out before the answer was known.
+    BudgetExhausted { steps_taken: usize },
+}
+
+impl Outcome {
+    pub fn steps_taken(&self) -> usize {
+        match self {
+            Outcome::Solved { steps_taken, .. }
+            | Outcome::Impossible { steps_taken }
+            | Outcome::BudgetExhausted { steps_taken } => *steps_taken,
+        }
+    }
+
+    pub fn placements(&self) -> Option<&[Placement]> {
+        match self {
+            Outcome::Solved { placements, .. } => Some(placements),
+            _ => None,
+        }
+    }
+}
+
+/// The result of [`Solver::count_solutions`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CountOutcome {
+    /// The first solution that was found, in the same order as in [`Outcome::Solved`].
+    pub first: Option<Vec<Placement>>,
+    /// Only exact if `has_finished` is true, otherwise it is a lower bound.
+    pub num_solutions: usize,
+    pub steps_taken: usize,
+    /// False if the step budget ran out before all solutions were found.
+    pub has_finished: bool,
+}
+
+/// The search algorithm. Both find the same solutions, but possibly in a different order, and
+/// their steps are not comparable.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// A depth-first search that branches on one tile at a time.
+    #[default]
+    DepthFirst,
+    /// Knuth's Algorithm X with Dancing Links. Usually faster on large boards, and when
+    /// counting all solutions.
+    DancingLinks,
+}
+
+/// Solves boards with a fixed set of tiles. By default, there is one copy of each tile, all
+/// rotations and reflections are allowed, and the search gives up after 10000 steps.
+#[derive(Clone, Debug)]
+pub struct Solver {
+    tiles: Vec<Tile>,
+    counts: Vec<Count>,
+    orientations: Vec<Orientation>,
+    heuristic: Arc<dyn Heuristic>,
+    backend: Backend,
+    max_steps: usize,
+    max_failed: usize,
+}
+
+impl Solver {
+    /// Panics if there are no tiles, or more than [`MAX_TILES`].
+    pub fn new(tiles: &[Tile]) -> Solver {
+        assert!(!tiles.is_empty() && tiles.len() <= MAX_TILES);
+        Solver {
+            tiles: tiles.to_vec(),
+            counts: vec![Count::Limited(1); tiles.len()],
+            orientations: vec![Orientation::RotationsAndReflections; tiles.len()],
+            heuristic: Arc::new(FewestPlacements),
+            backend: Backend::default(),
+            max_steps: DEFAULT_MAX_STEPS,
+            max_failed: search::DEFAULT_MAX_FAILED,
+        }
+    }
+
+    /// How many copies of each tile must be placed. Panics unless there is one count per tile.
+    pub fn counts(mut self, counts: Vec<Count>) -> Solver {
+        assert_eq!(self.tiles.len(), counts.len());
+        self.counts = counts;
+        self
+    }
+
+    /// The same orientation for all tiles.
+    pub fn orientation(mut self, orientation: Orientation) -> Solver {
+        self.orientations = vec![orientation; self.tiles.len()];
+        self
+    }
+
+    /// One orientation per tile, e.g. to keep tiles that are printed on one side only from being
+    /// flipped. Panics unless there is one orientation per tile.
+    pub fn orientations(mut self, orientations: Vec<Orientation>) -> Solver {
+        assert_eq!(self.tiles.len(), orientations.len());
+        self.orientations = orientations;
+        self
+    }
+
+    /// Only used by [`Backend::DepthFirst`]. See [`crate::heuristic::builtin`].
+    pub fn heuristic(mut self, heuristic: Arc<dyn Heuristic>) -> Solver {
+        self.heuristic = heuristic;
+        self
+    }
+
+    /// Up to how many dead ends the depth-first search remembers, to skip them when they are
+    /// reached again through a different order of placements. Zero disables this.
+    pub fn max_failed(mut self, max_failed: usize) -> Solver {
+        self.max_failed = max_failed;
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Solver {
+        self.backend = backend;
+        self
+    }
+
+    /// The step budget of each call to [`Solver::solve`] or [`Solver::count_solutions`].
+    pub fn max_steps(mut self, max_steps: usize) -> Solver {
+        self.max_steps = max_steps;
+        self
+    }
+
+    fn oriented_tiles(&self) -> Vec<Tile> {
+        self.tiles
+            .iter()
+            .zip(&self.orientations)
+            .map(|(tile, &orientation)| tile.with_orientation(orientation))
+            .collect()
+    }
+
+    // Up to 'max_solutions' solutions, with the same semantics for both backends.
+    fn find_solutions(&self, board: &Board, tiles: &[Tile], max_solutions: usize) -> Solutions {
+        match self.backend {
+            Backend::DepthFirst => {
+                let mut state =
+                    search::State::with_counts(board.clone(), tiles, self.counts.clone())
+                        .with_heuristic(&*self.heuristic)
+                        .with_max_failed(self.max_failed);
+                let mut solutions = state.solutions(self.max_steps);
+                let first = solutions.next();
+                let num_solutions = match first {
+                    Some(_) => 1 + solutions.by_ref().take(max_solutions - 1).count(),
+                    None => 0,
+                };
+                Solutions {
+                    first,
+                    num_solutions,
+                    steps_taken: solutions.steps_taken(),
+                    has_finished: solutions.has_finished(),
+                }
+            }
+            Backend::DancingLinks => {
+                dlx::solve(board, tiles, &self.counts, self.max_steps, max_solutions)
+            }
+        }
+    }
+
+    /// Searches for the first solution.
+    pub fn solve(&self, board: &Board) -> Outcome {
+        let tiles = self.oriented_tiles();
+        let solutions = self.find_solutions(board, &tiles, 1);
+        let steps_taken = solutions.steps_taken;
+        match solutions.first {
+            Some(steps) => Outcome::Solved {
+                placements: to_placements(&tiles, &steps),
+                steps_taken,
+            },
+            None if solutions.has_finished => Outcome::Impossible { steps_taken },
+            None => Outcome::BudgetExhausted { steps_taken },
+        }
+    }
+
+    /// Keeps searching after the first solution, e.g. to check that a puzzle is unique.
+    pub fn count_solutions(&self, board: &Board) -> CountOutcome {
+        let tiles = self.oriented_tiles();
+        let solutions = self.find_solutions(board, &tiles, usize::MAX);
+        CountOutcome {
+            first: solutions.first.map(|steps| to_placements(&tiles, &steps)),
+            num_solutions: solutions.num_solutions,
+            steps_taken: solutions.steps_taken,
+            has_finished: solutions.has_finished,
+        }
+    }
+
+    /// Starts a search for the first solution that can be continued step by step, and saved as a
+    /// checkpoint in between. Always uses [`Backend::DepthFirst`], and ignores the step budget.
+    pub fn start(&self, board: &Board) -> Search<'_> {
+        let state =
+            search::State::with_counts(board.clone(), &self.oriented_tiles(), self.counts.clone());
+        self.continue_search(state)
+    }
+
+    /// Continues a search from [`Search::to_checkpoint`]. The board and the counts are taken from
+    /// the checkpoint, the tiles and orientations must be the same as when it was saved.
+    pub fn resume(&self, checkpoint: &str) -> Result<Search<'_>, CheckpointError> {
+        let state = search::State::from_checkpoint(checkpoint, &self.oriented_tiles())?;
+        Ok(self.continue_search(state))
+    }
+
+    fn continue_search<'s>(&'s self, state: search::State<'s>) -> Search<'s> {
+        Search {
+            state: state
+                .with_heuristic(&*self.heuristic)
+                .with_max_failed(self.max_failed),
+            steps_taken: 0,
+            outcome: None,
+        }
+    }
+
+    /// Draws the placements as ASCII art, using the tile names where possible.
+    pub fn render(&self, board: &Board, placements: &[Placement]) -> String {
+        let steps = placements
+            .iter()
+            .map(Placement::to_operation)
+            .collect::<search::Result>();
+        ascii::render_solution(board, &steps, &self.oriented_tiles())
+    }
+}
+
+// 'steps' is leaf-first, placements are in the order in which they were placed.
+fn to_placements(tiles: &[Tile], steps: &search::Result) -> Vec<Placement> {
+    steps
+        .iter()
+        .rev()
+        .map(|operation| Placement::from_operation(tiles, operation))
+        .collect()
+}
+
+/// A search in progress, see [`Solver::start`].
+#[derive(Debug)]
+pub struct Search<'s> {
+    state: search::State<'s>,
+    steps_taken: usize,
+    outcome: Option<Outcome>,
+}
+
+impl Search<'_> {
+    /// Continues for at most `max_steps` steps. Returns the outcome once the search is done,
+    /// i.e. [`Outcome::Solved`] or [`Outcome::Impossible`].
+    pub fn step(&mut self, max_steps: usize) -> Option<&Outcome> {
+        if self.outcome.is_none() {
+            let (steps_done, result_maybe) = self.state.step_at_most(max_steps);
+            self.steps_taken += steps_done;
+            let steps_taken = self.steps_taken;
+            self.outcome = match result_maybe {
+                Some(steps) => Some(Outcome::Solved {
+                    placements: to_placements(self.state.tiles(), &steps),
+                    steps_taken,
+                }),
+                None if !self.state.can_step() => Some(Outcome::Impossible { steps_taken }),
+                None => None,
+            };
+        }
+        self.outcome.as_ref()
+    }
+
+    /// The steps since the search was started or resumed.
+    pub fn steps_taken(&self) -> usize {
+        self.steps_taken
+    }
+
+    /// A compact string from which [`Solver::resume`] can continue the search. A resumed search
+    /// finds the same solution, but might take a few more steps.
+    pub fn to_checkpoint(&self) -> String {
+        self.state.to_checkpoint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog;
+    use crate::heuristic::MostConstrainedCell;
+    use crate::puzzle::parse_counts;
+    use crate::{SolutionCount, Uniqueness};
+
+    fn ubongo_solver(tiles: &str) -> Solver {
+        let tiles_all = catalog::find("ubongo").unwrap().get_tiles();
+        Solver::new(tiles_all).counts(parse_counts(tiles, tiles_all).unwrap())
+    }
+
+    const SAMPLE_EASY: &str = "·XX··\nXXXX·\n·XXXX\n·XXX·";
+
+    #[test]
+    fn test_solve() {
+        let board = SAMPLE_EASY.parse().unwrap();
+        let solver = ubongo_solver("F G L");
+        let outcome = solver.solve(&board);
+        assert_eq!(outcome.steps_taken(), 13);
+        let placements = outcome.placements().unwrap();
+        let names = placements
+            .iter()
+            .map(|placement| solver.tiles[placement.get_tile_index()].get_name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["L", "G", "F"]);
+        assert_eq!(placements[0].get_offset(), (0, 0));
+        assert_eq!(
+            placements[0].cells().collect::<Vec<_>>(),
+            vec![(1, 0), (0, 1), (1, 1), (1, 2), (1, 3)]
+        );
+        assert_eq!(
+            solver.render(&board, placements),
+            "·LF··\nLLFF·\n·LFGG\n·LGG·"
+        );
+    }
+
+    #[test]
+    fn test_outcomes() {
+        let board: Board = SAMPLE_EASY.parse().unwrap();
+        assert_eq!(
+            ubongo_solver("F G L").max_steps(5).solve(&board),
+            Outcome::BudgetExhausted { steps_taken: 5 }
+        );
+        let outcome = ubongo_solver("F").solve(&"XXX".parse().unwrap());
+        assert!(matches!(outcome, Outcome::Impossible { .. }));
+        let count = ubongo_solver("F G L").count_solutions(&board);
+        assert_eq!(count.num_solutions, 1);
+        assert_eq!(
+            count.first.as_deref(),
+            ubongo_solver("F G L").solve(&board).placements()
+        );
+        assert_eq!(SolutionCount::from(count).uniqueness, Uniqueness::Unique);
+    }
+
+    #[test]
+    fn test_backends() {
+        let board: Board = SAMPLE_EASY.parse().unwrap();
+        for tiles in ["F G L", "F G H", "B C F", "A*inf"] {
+            let solver = ubongo_solver(tiles).max_steps(100_000);
+            let dfs = solver.count_solutions(&board);
+            let by_cell = solver
+                .clone()
+                .heuristic(Arc::new(MostConstrainedCell))
+                .count_solutions(&board);
+            let dlx = solver
+                .backend(Backend::DancingLinks)
+                .count_solutions(&board);
+            assert!(dfs.has_finished && by_cell.has_finished && dlx.has_finished);
+            assert_eq!(dfs.num_solutions, by_cell.num_solutions, "{}", tiles);
+            assert_eq!(dfs.num_solutions, dlx.num_solutions, "{}", tiles);
+        }
+        let solver = ubongo_solver("F G L").backend(Backend::DancingLinks);
+        let outcome = solver.solve(&board);
+        let placements = outcome.placements().unwrap();
+        assert_eq!(
+            solver.render(&board, placements),
+            "·LF··\nLLFF·\n·LFGG\n·LGG·"
+        );
+    }
+
+    #[test]
+    fn test_resume() {
+        let board: Board = SAMPLE_EASY.parse().unwrap();
+        let solver = ubongo_solver("F G L");
+        let mut search = solver.start(&board);
+        assert_eq!(search.step(5), None);
+        assert_eq!(search.steps_taken(), 5);
+        let mut resumed = solver.resume(&search.to_checkpoint()).unwrap();
+        let outcome = resumed.step(1000).unwrap();
+        assert_eq!(outcome.placements(), solver.solve(&board).placements());
+        assert!(solver.resume("mebongo-search 2").is_err());
+        let solver = ubongo_solver("F");
+        let mut impossible = solver.start(&"XXX".parse().unwrap());
+        assert!(matches!(
+            impossible.step(1000),
+            Some(Outcome::Impossible { .. })
+        ));
+    }
+
+    #[test]
+    fn test_orientation() {
+        // The L tile only fits this board when mirrored.
+        let board: Board = "XXXX\n···X".parse().unwrap();
+        let solver = Solver::new(&[catalog::find("tetrominoes").unwrap().get_tiles()[4].clone()]);
+        let placements = solver.solve(&board).placements().unwrap().to_vec();
+        assert!(placements[0].get_transform().mirrored);
+        let solver = solver.orientation(Orientation::Rotations);
+        assert!(matches!(solver.solve(&board), Outcome::Impossible { .. }));
+    }
+
+    #[test]
+    fn test_orientations() {
+        // Two L tiles, one of which is printed on one side only, and one mirrored L fits.
+        let board: Board = "XXXX\n···X\n····\nXXXX\nX···".parse().unwrap();
+        let l_tile = catalog::find("tetrominoes").unwrap().get_tiles()[4].clone();
+        let solver = Solver::new(&[l_tile.clone(), l_tile]);
+        assert!(solver.solve(&board).placements().is_some());
+        let one_sided = solver.clone().orientations(vec![
+            Orientation::Rotations,
+            Orientation::RotationsAndReflections,
+        ]);
+        let placements = one_sided.solve(&board).placements().unwrap().to_vec();
+        assert!(placements.iter().all(
+            |placement| placement.get_tile_index() == 1 || !placement.get_transform().mirrored
+        ));
+        let both_one_sided = solver.orientation(Orientation::Rotations);
+        assert!(matches!(
+            both_one_sided.solve(&board),
+            Outcome::Impossible { .. }
+        ));
+    }
+}
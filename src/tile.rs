@@ -1,18 +1,124 @@
-// This is assumed to be 4 in several places
-pub const MAX_SIZE: u8 = 4;
+use crate::ascii::{self, ParseError};
+use std::fmt;
+use std::str::FromStr;
+
+// Tile layouts are stored as one byte per row, so this can't be increased beyond 8.
+pub const MAX_SIZE: u8 = 8;
+
+// How a layout was derived from the canonical shape of its tile: First mirror (if at all),
+// then rotate clockwise by the given number of quarter turns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transform {
+    pub mirrored: bool,
+    pub rotation: u8,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        mirrored: false,
+        rotation: 0,
+    };
+}
+
+// Which layouts of a tile may be used, e.g. because a piece is printed on one side only.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Fixed,
+    Rotations,
+    RotationsAndReflections,
+}
+
+impl Orientation {
+    pub fn allows(self, transform: Transform) -> bool {
+        match self {
+            Orientation::Fixed => transform == Transform::IDENTITY,
+            Orientation::Rotations => !transform.mirrored,
+            Orientation::RotationsAndReflections => true,
+        }
+    }
+}
+
+// How many copies of a tile are available.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Count {
+    Limited(u8),
+    // As many as needed, e.g. for pure tiling questions. Boards must then be covered completely.
+    Unlimited,
+}
+
+// Why a shape cannot be used as a tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShapeError {
+    Empty,
+    Disconnected,
+}
 
 #[derive(Clone, Debug)]
 pub struct Tile {
+    // For example the letter printed on the physical piece. Empty if the tile has no name.
+    name: String,
     layouts: Vec<TileLayout>,
+    // Parallel to 'layouts'.
+    transforms: Vec<Transform>,
 }
 
 impl Tile {
-    fn new(layouts_raw: Vec<u16>) -> Tile {
+    // All distinct rotations and reflections of the given shape, normalized to the top-left
+    // corner. Layout 0 is the normalized shape itself.
+    // If several transforms result in the same layout, the one without mirroring and with the
+    // fewest rotations is recorded, so that 'with_orientation' keeps as many layouts as possible.
+    pub fn from_shape(shape: &TileLayout) -> Tile {
+        let mut layouts: Vec<TileLayout> = Vec::with_capacity(8);
+        let mut transforms = Vec::with_capacity(8);
+        for mirrored in [false, true] {
+            let mut layout = if mirrored {
+                shape.mirrored()
+            } else {
+                shape.normalized()
+            };
+            for rotation in 0..4 {
+                let next_layout = layout.rotated();
+                if !layouts.contains(&layout) {
+                    layouts.push(layout);
+                    transforms.push(Transform { mirrored, rotation });
+                }
+                layout = next_layout;
+            }
+        }
+        Tile {
+            name: String::new(),
+            layouts,
+            transforms,
+        }
+    }
+
+    pub fn named(mut self, name: &str) -> Tile {
+        self.name = name.to_string();
+        self
+    }
+
+    // Same as 'from_shape', but with the hand-picked order of layouts given by the caller,
+    // since that order determines the search order.
+    fn new(name: &str, layouts_raw: Vec<u16>) -> Tile {
+        let mut tile = Tile::new_unchecked(layouts_raw).named(name);
+        let generated = Tile::from_shape(&tile.layouts[0]);
+        assert_eq!(tile.layouts.len(), generated.layouts.len());
+        for (layout, transform) in tile.layouts.iter().zip(tile.transforms.iter_mut()) {
+            let generated_index = generated
+                .layouts
+                .iter()
+                .position(|l| l == layout)
+                .expect("layout is not an orientation of the first layout");
+            *transform = generated.transforms[generated_index];
+        }
+        tile
+    }
+
+    fn new_unchecked(layouts_raw: Vec<u16>) -> Tile {
         let tile = Tile {
-            layouts: layouts_raw
-                .into_iter()
-                .map(|bit_data| TileLayout { bit_data })
-                .collect(),
+            name: String::new(),
+            transforms: vec![Transform::IDENTITY; layouts_raw.len()],
+            layouts: layouts_raw.into_iter().map(TileLayout::from_4x4).collect(),
         };
         let size = tile.layouts[0].size();
         debug_assert!(tile.layouts.iter().all(|l| l.size() == size));
@@ -21,7 +127,28 @@ impl Tile {
 
     #[cfg(test)]
     pub fn new_for_test(layouts_raw: Vec<u16>) -> Tile {
-        Tile::new(layouts_raw)
+        Tile::new_unchecked(layouts_raw)
+    }
+
+    // Only keeps the layouts that the given orientation policy allows. This never removes
+    // layout 0, so every tile can still be placed somehow.
+    pub fn with_orientation(&self, orientation: Orientation) -> Tile {
+        let (layouts, transforms) = self
+            .layouts
+            .iter()
+            .zip(&self.transforms)
+            .filter(|(_, &transform)| orientation.allows(transform))
+            .map(|(layout, &transform)| (layout.clone(), transform))
+            .unzip();
+        Tile {
+            name: self.name.clone(),
+            layouts,
+            transforms,
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
     }
 
     pub fn get_size(&self) -> u32 {
@@ -31,28 +158,183 @@ impl Tile {
     pub fn get_layouts(&self) -> &[TileLayout] {
         &self.layouts
     }
+
+    pub fn get_transform(&self, layout_index: u8) -> Transform {
+        self.transforms[usize::from(layout_index)]
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct TileLayout {
-    bit_data: u16,
+    bit_data: u64,
 }
 
 impl TileLayout {
-    pub fn is_present_at(&self, x: u8, y: u8) -> bool {
+    pub fn from_cells(cells: &[(u8, u8)]) -> TileLayout {
+        let mut layout = TileLayout { bit_data: 0 };
+        for &(x, y) in cells {
+            layout.bit_data |= Self::mask(x, y);
+        }
+        layout
+    }
+
+    // One byte per row, starting at the top. Bit x (LSB first) marks the cell in column x.
+    pub fn from_rows(rows: &[u8]) -> TileLayout {
+        assert!(rows.len() <= usize::from(MAX_SIZE));
+        let mut layout = TileLayout { bit_data: 0 };
+        for (y, &row) in rows.iter().enumerate() {
+            layout.bit_data |= u64::from(row) << (y * usize::from(MAX_SIZE));
+        }
+        layout
+    }
+
+    // Inverse of 'from_rows'.
+    pub fn to_rows(&self) -> [u8; MAX_SIZE as usize] {
+        self.bit_data.to_le_bytes()
+    }
+
+    // The compact notation used in 'ALL_TILES', with 4 bits per row.
+    fn from_4x4(bits: u16) -> TileLayout {
+        let mut layout = TileLayout { bit_data: 0 };
+        for y in 0..4 {
+            let row = u64::from((bits >> (4 * y)) & 0xF);
+            layout.bit_data |= row << (y * MAX_SIZE);
+        }
+        layout
+    }
+
+    fn mask(x: u8, y: u8) -> u64 {
         assert!(x < MAX_SIZE && y < MAX_SIZE);
         let offset = x + y * MAX_SIZE;
-        0 != (self.bit_data & (1 << offset))
+        1 << offset
+    }
+
+    pub fn is_present_at(&self, x: u8, y: u8) -> bool {
+        0 != (self.bit_data & Self::mask(x, y))
     }
 
     #[cfg(test)]
-    pub fn new_for_test(bit_data: u16) -> TileLayout {
-        TileLayout { bit_data }
+    pub fn new_for_test(bits: u16) -> TileLayout {
+        TileLayout::from_4x4(bits)
     }
 
     fn size(&self) -> u32 {
         self.bit_data.count_ones()
     }
+
+    // Tiles must be polyominoes, i.e. non-empty and connected through their edges.
+    pub fn check_shape(&self) -> Result<(), ShapeError> {
+        const FIRST_COLUMN: u64 = 0x0101_0101_0101_0101;
+        const LAST_COLUMN: u64 = FIRST_COLUMN << (MAX_SIZE - 1);
+        if self.bit_data == 0 {
+            return Err(ShapeError::Empty);
+        }
+        // Flood fill, starting at the lowest cell:
+        let mut reached = self.bit_data & self.bit_data.wrapping_neg();
+        loop {
+            let grown = (reached
+                | ((reached & !LAST_COLUMN) << 1)
+                | ((reached & !FIRST_COLUMN) >> 1)
+                | (reached << MAX_SIZE)
+                | (reached >> MAX_SIZE))
+                & self.bit_data;
+            if grown == reached {
+                break;
+            }
+            reached = grown;
+        }
+        if reached == self.bit_data {
+            Ok(())
+        } else {
+            Err(ShapeError::Disconnected)
+        }
+    }
+
+    // Row by row, starting at the top-left.
+    pub fn cells(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        (0..MAX_SIZE)
+            .flat_map(|y| (0..MAX_SIZE).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.is_present_at(x, y))
+    }
+
+    // Width and height of the smallest rectangle at the origin that contains all cells.
+    pub fn bounding_box(&self) -> (u8, u8) {
+        self.cells()
+            .fold((0, 0), |(w, h), (x, y)| (w.max(x + 1), h.max(y + 1)))
+    }
+
+    // Moves the cells as far to the top-left as possible.
+    pub fn normalized(&self) -> TileLayout {
+        let min_x = self.cells().map(|(x, _)| x).min().unwrap_or(0);
+        let min_y = self.cells().map(|(_, y)| y).min().unwrap_or(0);
+        let cells = self
+            .cells()
+            .map(|(x, y)| (x - min_x, y - min_y))
+            .collect::<Vec<_>>();
+        TileLayout::from_cells(&cells)
+    }
+
+    // Rotates by 90 degrees clockwise, and normalizes the result.
+    pub fn rotated(&self) -> TileLayout {
+        let cells = self
+            .cells()
+            .map(|(x, y)| (MAX_SIZE - 1 - y, x))
+            .collect::<Vec<_>>();
+        TileLayout::from_cells(&cells).normalized()
+    }
+
+    // Mirrors along the vertical axis, and normalizes the result.
+    pub fn mirrored(&self) -> TileLayout {
+        let cells = self
+            .cells()
+            .map(|(x, y)| (MAX_SIZE - 1 - x, y))
+            .collect::<Vec<_>>();
+        TileLayout::from_cells(&cells).normalized()
+    }
+}
+
+// Row by row, cropped to the bounding box.
+impl fmt::Display for TileLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (width, height) = self.bounding_box();
+        for y in 0..height {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for x in 0..width {
+                let c = if self.is_present_at(x, y) {
+                    ascii::PRESENT
+                } else {
+                    ascii::ABSENT
+                };
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The cells are kept where they are drawn, without normalizing.
+impl FromStr for TileLayout {
+    type Err = ParseError;
+
+    fn from_str(drawing: &str) -> Result<TileLayout, ParseError> {
+        let grid = ascii::parse_grid(drawing)?;
+        if grid.len() > usize::from(MAX_SIZE) || grid[0].len() > usize::from(MAX_SIZE) {
+            return Err(ParseError::TooLarge);
+        }
+        let mut cells = Vec::new();
+        for (y, row) in grid.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                if c == ascii::PRESENT {
+                    cells.push((x as u8, y as u8));
+                } else if !ascii::is_absent(c) {
+                    return Err(ParseError::UnexpectedCharacter(c));
+                }
+            }
+        }
+        Ok(TileLayout::from_cells(&cells))
+    }
 }
 
 lazy_static! {
@@ -63,7 +345,7 @@ lazy_static! {
          * 4 5 6 7
          * 8 9 A B
          * C D E F
-         * … where 0 is the LSB and F is the MSB of the underlying u16.
+         * … where 0 is the LSB and F is the MSB of the u16 given to 'from_4x4'.
          */
         /*
          * XX·· 3 A
@@ -71,84 +353,84 @@ lazy_static! {
          * ···· 0
          * ···· 0
          */
-        Tile::new(vec![0x0033]),
+        Tile::new("A", vec![0x0033]),
         /*
          * XX·· 3 X··· 1 B
          * ···· 0 X··· 1
          * ···· 0 ···· 0
          * ···· 0 ···· 0
          */
-        Tile::new(vec![0x0003, 0x0011]),
+        Tile::new("B", vec![0x0003, 0x0011]),
         /*
          * XXX· 7 X··· 1 C
          * ···· 0 X··· 1
          * ···· 0 X··· 1
          * ···· 0 ···· 0
          */
-        Tile::new(vec![0x0007, 0x0111]),
+        Tile::new("C", vec![0x0007, 0x0111]),
         /*
          * XXXX F X··· 1 D
          * ···· 0 X··· 1
          * ···· 0 X··· 1
          * ···· 0 X··· 1
          */
-        Tile::new(vec![0x000F, 0x1111]),
+        Tile::new("D", vec![0x000F, 0x1111]),
         /*
          * XX·· 3 XX·· 3 ·X·· 2 X··· 1 E
          * X··· 1 ·X·· 2 XX·· 3 XX·· 3
          * ···· 0 ···· 0 ···· 0 ···· 0
          * ···· 0 ···· 0 ···· 0 ···· 0
          */
-        Tile::new(vec![0x0013, 0x0023, 0x0032, 0x0031]),
+        Tile::new("E", vec![0x0013, 0x0023, 0x0032, 0x0031]),
         /*
          * ·X·· 2 X··· 1 XXX· 7 ·X·· 2 F
          * XXX· 7 XX·· 3 ·X·· 2 XX·· 3
          * ···· 0 X··· 1 ···· 0 ·X·· 2
          * ···· 0 ···· 0 ···· 0 ···· 0
          */
-        Tile::new(vec![0x0072, 0x0131, 0x0027, 0x0232]),
+        Tile::new("F", vec![0x0072, 0x0131, 0x0027, 0x0232]),
         /*
          * XX·· 3 ·XX· 6 X··· 1 ·X·· 2 G
          * ·XX· 6 XX·· 3 XX·· 3 XX·· 3
          * ···· 0 ···· 0 ·X·· 2 X··· 1
          * ···· 0 ···· 0 ···· 0 ···· 0
          */
-        Tile::new(vec![0x0063, 0x0036, 0x0231, 0x0132]),
+        Tile::new("G", vec![0x0063, 0x0036, 0x0231, 0x0132]),
         /*
          * XX·· 3 ·XX· 6 X··· 1 ··X· 4 H
          * ·X·· 2 ·X·· 2 XXX· 7 XXX· 7
          * ·XX· 6 XX·· 3 ··X· 4 X··· 1
          * ···· 0 ···· 0 ···· 0 ···· 0
          */
-        Tile::new(vec![0x0623, 0x0326, 0x0471, 0x0174]),
+        Tile::new("H", vec![0x0623, 0x0326, 0x0471, 0x0174]),
         /*
          * XX·· 3 XXX· 7 ·X·· 2 X··· 1 XX·· 3 XXX· 7 X··· 1 ··X· 4 J (intentionally skip "I")
          * X··· 1 ··X· 4 ·X·· 2 XXX· 7 ·X·· 2 X··· 1 X··· 1 XXX· 7
          * X··· 1 ···· 0 XX·· 3 ···· 0 ·X·· 2 ···· 0 XX·· 3 ···· 0
          * ···· 0 ···· 0 ···· 0 ···· 0 ···· 0 ···· 0 ···· 0 ···· 0
          */
-        Tile::new(vec![0x0113, 0x0047, 0x0322, 0x0071, 0x0223, 0x0017, 0x0311, 0x0074]),
+        Tile::new("J", vec![0x0113, 0x0047, 0x0322, 0x0071, 0x0223, 0x0017, 0x0311, 0x0074]),
         /*
          * XX·· 3 XXXX F ·X·· 2 X··· 1 XX·· 3 XXXX F X··· 1 ···X 8 K
          * X··· 1 ···X 8 ·X·· 2 XXXX F ·X·· 2 X··· 1 X··· 1 XXXX F
          * X··· 1 ···· 0 ·X·· 2 ···· 0 ·X·· 2 ···· 0 X··· 1 ···· 0
          * X··· 1 ···· 0 XX·· 3 ···· 0 ·X·· 2 ···· 0 XX·· 3 ···· 0
          */
-        Tile::new(vec![0x1113, 0x008F, 0x3222, 0x00F1, 0x2223, 0x001F, 0x3111, 0x00F8]),
+        Tile::new("K", vec![0x1113, 0x008F, 0x3222, 0x00F1, 0x2223, 0x001F, 0x3111, 0x00F8]),
         /*
          * X··· 1 XXXX F ·X·· 2 ·X·· 2 ·X·· 2 XXXX F X··· 1 ··X· 4 L
          * XX·· 3 ··X· 4 ·X·· 2 XXXX F XX·· 3 ·X·· 2 X··· 1 XXXX F
          * X··· 1 ···· 0 XX·· 3 ···· 0 ·X·· 2 ···· 0 XX·· 3 ···· 0
          * X··· 1 ···· 0 ·X·· 2 ···· 0 ·X·· 2 ···· 0 X··· 1 ···· 0
          */
-        Tile::new(vec![0x1131, 0x004F, 0x2322, 0x00F2, 0x2232, 0x002F, 0x1311, 0x00F4]),
+        Tile::new("L", vec![0x1131, 0x004F, 0x2322, 0x00F2, 0x2232, 0x002F, 0x1311, 0x00F4]),
         /*
          * XX·· 3 XX·· 3 ·X·· 2 XXX· 7 XX·· 3 XXX· 7 X··· 1 ·XX· 6 M
          * XX·· 3 XXX· 7 XX·· 3 ·XX· 6 XX·· 3 XX·· 3 XX·· 3 XXX· 7
          * X··· 1 ···· 0 XX·· 3 ···· 0 ·X·· 2 ···· 0 XX·· 3 ···· 0
          * ···· 0 ···· 0 ···· 0 ···· 0 ···· 0 ···· 0 ···· 0 ···· 0
          */
-        Tile::new(vec![0x0133, 0x0073, 0x0332, 0x0067, 0x0233, 0x0037, 0x0331, 0x0076]),
+        Tile::new("M", vec![0x0133, 0x0073, 0x0332, 0x0067, 0x0233, 0x0037, 0x0331, 0x0076]),
     ];
 }
 
@@ -169,4 +451,232 @@ mod tests {
         println!("{:?}", layout_bits_dedup);
         assert_eq!(layout_bits.len(), layout_bits_dedup.len());
     }
+
+    #[test]
+    fn test_layouts_are_complete() {
+        for tile in ALL_TILES.iter() {
+            let generated = Tile::from_shape(&tile.get_layouts()[0]);
+            let expected = generated.get_layouts().iter().collect::<HashSet<_>>();
+            let actual = tile.get_layouts().iter().collect::<HashSet<_>>();
+            assert_eq!(expected, actual);
+            for layout in tile.get_layouts() {
+                assert_eq!(layout, &layout.normalized());
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_shape_symmetries() {
+        // XX··
+        // XX··
+        let square = TileLayout::from_cells(&[(0, 0), (1, 0), (0, 1), (1, 1)]);
+        assert_eq!(Tile::from_shape(&square).get_layouts().len(), 1);
+        // ·X··
+        // XXX·
+        let tee = TileLayout::from_cells(&[(1, 0), (0, 1), (1, 1), (2, 1)]);
+        assert_eq!(Tile::from_shape(&tee).get_layouts().len(), 4);
+        // XX··
+        // ·XX·
+        let zig = TileLayout::from_cells(&[(0, 0), (1, 0), (1, 1), (2, 1)]);
+        assert_eq!(Tile::from_shape(&zig).get_layouts().len(), 4);
+        // X···
+        // X···
+        // XX··
+        let ell = TileLayout::from_cells(&[(0, 0), (0, 1), (0, 2), (1, 2)]);
+        assert_eq!(Tile::from_shape(&ell).get_layouts().len(), 8);
+    }
+
+    #[test]
+    fn test_from_shape_normalizes() {
+        // ····
+        // ··X·
+        // ··XX
+        let shape = TileLayout::from_cells(&[(2, 1), (2, 2), (3, 2)]);
+        let tile = Tile::from_shape(&shape);
+        assert_eq!(tile.get_layouts()[0], TileLayout::new_for_test(0x0031));
+        assert_eq!(tile.get_size(), 3);
+    }
+
+    #[test]
+    fn test_rotate_mirror() {
+        // XX·· 3 -> XXX· 7
+        // X··· 1    ··X· 4
+        // X··· 1
+        let ell = TileLayout::new_for_test(0x0113);
+        assert_eq!(ell.rotated(), TileLayout::new_for_test(0x0047));
+        assert_eq!(ell.rotated().rotated().rotated().rotated(), ell);
+        assert_eq!(ell.mirrored(), TileLayout::new_for_test(0x0223));
+        assert_eq!(ell.mirrored().mirrored(), ell);
+    }
+
+    #[test]
+    fn test_bounding_box_and_cells() {
+        let ell = TileLayout::new_for_test(0x0113);
+        assert_eq!(ell.bounding_box(), (2, 3));
+        assert_eq!(ell.rotated().bounding_box(), (3, 2));
+        assert_eq!(
+            ell.cells().collect::<Vec<_>>(),
+            vec![(0, 0), (1, 0), (0, 1), (0, 2)]
+        );
+        assert_eq!(TileLayout::new_for_test(0x8000).bounding_box(), (4, 4));
+        assert_eq!(TileLayout::new_for_test(0x0000).bounding_box(), (0, 0));
+    }
+
+    #[test]
+    fn test_from_4x4() {
+        let layout = TileLayout::new_for_test(0x8421);
+        assert_eq!(
+            layout.cells().collect::<Vec<_>>(),
+            vec![(0, 0), (1, 1), (2, 2), (3, 3)]
+        );
+    }
+
+    #[test]
+    fn test_large_shapes() {
+        let bar = (0..6).map(|x| (x, 0)).collect::<Vec<_>>();
+        let bar = Tile::from_shape(&TileLayout::from_cells(&bar));
+        assert_eq!(bar.get_size(), 6);
+        assert_eq!(bar.get_layouts().len(), 2);
+        assert_eq!(bar.get_layouts()[1].bounding_box(), (1, 6));
+        // X·······
+        // XXXXXXXX
+        let long_ell = (0..8).map(|x| (x, 1)).chain([(0, 0)]).collect::<Vec<_>>();
+        let long_ell = Tile::from_shape(&TileLayout::from_cells(&long_ell));
+        assert_eq!(long_ell.get_size(), 9);
+        assert_eq!(long_ell.get_layouts().len(), 8);
+        for layout in long_ell.get_layouts() {
+            let (w, h) = layout.bounding_box();
+            assert_eq!(w.max(h), 8);
+            assert_eq!(w.min(h), 2);
+        }
+    }
+
+    #[test]
+    fn test_transforms() {
+        // Layouts of "J", see above.
+        let tile = &ALL_TILES[8];
+        let expected = [
+            (false, 0),
+            (false, 1),
+            (false, 2),
+            (false, 3),
+            (true, 0),
+            (true, 3),
+            (true, 2),
+            (true, 1),
+        ];
+        for (layout_index, &(mirrored, rotation)) in expected.iter().enumerate() {
+            assert_eq!(
+                tile.get_transform(layout_index as u8),
+                Transform { mirrored, rotation }
+            );
+        }
+    }
+
+    #[test]
+    fn test_with_orientation() {
+        let j = &ALL_TILES[8];
+        assert_eq!(
+            j.with_orientation(Orientation::Fixed).get_layouts().len(),
+            1
+        );
+        assert_eq!(
+            j.with_orientation(Orientation::Rotations).get_layouts(),
+            &j.get_layouts()[0..4]
+        );
+        assert_eq!(
+            j.with_orientation(Orientation::RotationsAndReflections)
+                .get_layouts(),
+            j.get_layouts()
+        );
+        // The mirror image of "E" is just a rotation, so nothing gets lost:
+        let e = &ALL_TILES[4];
+        assert_eq!(
+            e.with_orientation(Orientation::Rotations)
+                .get_layouts()
+                .len(),
+            4
+        );
+        // The mirror image of "G" isn't, and its rotations are all symmetric:
+        let g = &ALL_TILES[6];
+        let g_rotations = g.with_orientation(Orientation::Rotations);
+        assert_eq!(g_rotations.get_layouts().len(), 2);
+        assert!(g_rotations
+            .get_layouts()
+            .iter()
+            .all(|l| g_rotations.get_layouts().contains(&l.rotated())));
+    }
+
+    #[test]
+    fn test_from_rows() {
+        assert_eq!(
+            TileLayout::from_rows(&[0x03, 0x01, 0x01]),
+            TileLayout::new_for_test(0x0113)
+        );
+        assert_eq!(
+            TileLayout::new_for_test(0x0113).to_rows(),
+            [0x03, 0x01, 0x01, 0, 0, 0, 0, 0]
+        );
+        let bar = TileLayout::from_rows(&[0xFF; 8]);
+        assert_eq!(bar.size(), 64);
+        assert_eq!(bar.bounding_box(), (8, 8));
+    }
+
+    #[test]
+    fn test_check_shape() {
+        for tile in ALL_TILES.iter() {
+            assert_eq!(tile.get_layouts()[0].check_shape(), Ok(()));
+        }
+        assert_eq!(
+            TileLayout::from_rows(&[]).check_shape(),
+            Err(ShapeError::Empty)
+        );
+        // X·X·
+        assert_eq!(
+            TileLayout::from_rows(&[0x05]).check_shape(),
+            Err(ShapeError::Disconnected)
+        );
+        // X···
+        // ·X··
+        assert_eq!(
+            TileLayout::from_rows(&[0x01, 0x02]).check_shape(),
+            Err(ShapeError::Disconnected)
+        );
+        // Must not wrap around from the last column to the next row:
+        // ·······X
+        // X·······
+        assert_eq!(
+            TileLayout::from_rows(&[0x80, 0x01]).check_shape(),
+            Err(ShapeError::Disconnected)
+        );
+        // XXX·····
+        // X·X·····
+        // X·XXXXXX
+        assert_eq!(
+            TileLayout::from_rows(&[0x07, 0x05, 0xFD]).check_shape(),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        let ell = TileLayout::new_for_test(0x0113);
+        assert_eq!(ell.to_string(), "XX\nX·\nX·");
+        assert_eq!("XX\nX·\nX·".parse(), Ok(ell.clone()));
+        assert_eq!("XX·\nX..\nX··".parse(), Ok(ell));
+        assert_eq!(
+            "···\n·XX".parse::<TileLayout>().map(|l| l.normalized()),
+            Ok(TileLayout::new_for_test(0x0003))
+        );
+        assert_eq!("XXXXXXXXX".parse::<TileLayout>(), Err(ParseError::TooLarge));
+        assert_eq!(
+            "X#".parse::<TileLayout>(),
+            Err(ParseError::UnexpectedCharacter('#'))
+        );
+        for tile in ALL_TILES.iter() {
+            for layout in tile.get_layouts() {
+                assert_eq!(layout.to_string().parse().as_ref(), Ok(layout));
+            }
+        }
+    }
 }